@@ -0,0 +1,206 @@
+//! User-defined message describing a check result, e.g. the text of an alert sent
+//! by an `--on-result-exec` script.
+//!
+//! Placeholders are validated when the template is parsed, so a typo fails at
+//! startup instead of producing broken alerts at the first outage.
+
+use anyhow::{Result, bail};
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Template with `{endpoint}`, `{state}`, `{error}`, `{latency}` and `{chain}`
+/// placeholders.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(into = "String")]
+pub struct AlertTemplate {
+    /// Literal text and placeholders, in order.
+    segments: Vec<Segment>,
+}
+
+/// Part of a parsed template.
+#[derive(Clone, Debug, PartialEq)]
+enum Segment {
+    /// Text copied as is.
+    Literal(String),
+    /// Placeholder replaced by its value.
+    Placeholder(Placeholder),
+}
+
+/// Value that can be substituted into a template.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Placeholder {
+    /// `{endpoint}`: the monitored endpoint.
+    Endpoint,
+    /// `{state}`: `up` or `down`.
+    State,
+    /// `{error}`: the error chain of a failed check.
+    Error,
+    /// `{latency}`: the check duration in seconds.
+    Latency,
+    /// `{chain}`: the chain name or ID reported by the node.
+    Chain,
+}
+
+impl Placeholder {
+    /// All placeholders, in the order they are listed in messages.
+    const ALL: [Placeholder; 5] = [
+        Placeholder::Endpoint,
+        Placeholder::State,
+        Placeholder::Error,
+        Placeholder::Latency,
+        Placeholder::Chain,
+    ];
+
+    /// Name of the placeholder between the braces.
+    fn name(self) -> &'static str {
+        match self {
+            Placeholder::Endpoint => "endpoint",
+            Placeholder::State => "state",
+            Placeholder::Error => "error",
+            Placeholder::Latency => "latency",
+            Placeholder::Chain => "chain",
+        }
+    }
+}
+
+/// Values substituted for the placeholders of a template.
+pub struct AlertValues<'a> {
+    /// The monitored endpoint (redacted).
+    pub endpoint: &'a str,
+    /// Whether the endpoint is considered up after the check.
+    pub up: bool,
+    /// Error chain of a failed check.
+    pub error: Option<&'a str>,
+    /// Total duration of the check.
+    pub latency: Duration,
+    /// Chain name or ID reported by the node, if known.
+    pub chain: Option<&'a str>,
+}
+
+impl FromStr for AlertTemplate {
+    type Err = anyhow::Error;
+
+    fn from_str(template: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find(['{', '}']) {
+            if rest[start..].starts_with('}') {
+                bail!("Unmatched '}}' in template '{template}'");
+            }
+            let Some(end) = rest[start..].find('}') else {
+                bail!("Unclosed '{{' in template '{template}'");
+            };
+            let name = &rest[start + 1..start + end];
+            let Some(placeholder) = Placeholder::ALL.into_iter().find(|p| p.name() == name) else {
+                bail!(
+                    "Unknown placeholder '{{{name}}}', expected one of {}",
+                    Placeholder::ALL
+                        .map(|p| format!("{{{}}}", p.name()))
+                        .join(", ")
+                );
+            };
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_owned()));
+            }
+            segments.push(Segment::Placeholder(placeholder));
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_owned()));
+        }
+
+        Ok(Self { segments })
+    }
+}
+
+impl fmt::Display for AlertTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => f.write_str(text)?,
+                Segment::Placeholder(placeholder) => write!(f, "{{{}}}", placeholder.name())?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<AlertTemplate> for String {
+    fn from(template: AlertTemplate) -> Self {
+        template.to_string()
+    }
+}
+
+impl AlertTemplate {
+    /// Renders the template, replacing unknown values with an empty string.
+    ///
+    /// `{state}` is `up` or `down`, and `{latency}` is in seconds.
+    pub fn render(&self, values: &AlertValues) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) => text.clone(),
+                Segment::Placeholder(Placeholder::Endpoint) => values.endpoint.to_owned(),
+                Segment::Placeholder(Placeholder::State) => {
+                    (if values.up { "up" } else { "down" }).to_owned()
+                }
+                Segment::Placeholder(Placeholder::Error) => {
+                    values.error.unwrap_or_default().to_owned()
+                }
+                Segment::Placeholder(Placeholder::Latency) => {
+                    format!("{:.3}", values.latency.as_secs_f64())
+                }
+                Segment::Placeholder(Placeholder::Chain) => {
+                    values.chain.unwrap_or_default().to_owned()
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AlertTemplate, AlertValues};
+    use std::time::Duration;
+
+    #[test]
+    fn renders_placeholders() {
+        let template: AlertTemplate = "[{chain}] {endpoint} is {state}: {error} ({latency}s)"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            template.to_string(),
+            "[{chain}] {endpoint} is {state}: {error} ({latency}s)"
+        );
+
+        let values = AlertValues {
+            endpoint: "wss://node",
+            up: false,
+            error: Some("Connection refused"),
+            latency: Duration::from_millis(1500),
+            chain: Some("Polkadot"),
+        };
+        assert_eq!(
+            template.render(&values),
+            "[Polkadot] wss://node is down: Connection refused (1.500s)"
+        );
+
+        let values = AlertValues {
+            up: true,
+            error: None,
+            chain: None,
+            ..values
+        };
+        assert_eq!(template.render(&values), "[] wss://node is up:  (1.500s)");
+    }
+
+    #[test]
+    fn rejects_invalid_templates() {
+        for template in ["{host} down", "{endpoint", "endpoint}", "{}"] {
+            assert!(template.parse::<AlertTemplate>().is_err(), "{template}");
+        }
+        assert!("no placeholders".parse::<AlertTemplate>().is_ok());
+    }
+}
@@ -0,0 +1,71 @@
+//! Estimate of the block production rate from consecutive finalized block numbers.
+//!
+//! A dropping rate reveals degraded block production or finality before the head
+//! stalls completely. Times are measured with tokio's clock, so tests can pause
+//! and advance time instead of sleeping.
+
+use std::sync::Mutex;
+use tokio::time::Instant;
+
+/// Derives blocks per second from the block numbers reported by successive checks.
+#[derive(Default)]
+pub struct BlockRate(Mutex<Estimate>);
+
+/// Observations the estimate is derived from.
+#[derive(Default)]
+struct Estimate {
+    /// Last observed block number and when it was observed.
+    last: Option<(u64, Instant)>,
+    /// Blocks per second between the last two usable observations.
+    rate: Option<f64>,
+}
+
+impl BlockRate {
+    /// Records the block number reported by a check, updating the estimate.
+    ///
+    /// A lower number than before, e.g. after a node restart or resync, only
+    /// becomes the new baseline; the previous estimate is kept.
+    pub fn observe(&self, number: u64) {
+        let now = Instant::now();
+        let mut estimate = self.0.lock().unwrap();
+        if let Some((last_number, last_at)) = estimate.last {
+            let elapsed = now.duration_since(last_at).as_secs_f64();
+            if number >= last_number && elapsed > 0.0 {
+                estimate.rate = Some((number - last_number) as f64 / elapsed);
+            }
+        }
+        estimate.last = Some((number, now));
+    }
+
+    /// Returns the blocks per second between the last two observations, if known.
+    pub fn blocks_per_second(&self) -> Option<f64> {
+        self.0.lock().unwrap().rate
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BlockRate;
+    use std::time::Duration;
+    use tokio::time;
+
+    #[tokio::test(start_paused = true)]
+    async fn estimates_rate_and_skips_resets() {
+        let rate = BlockRate::default();
+        rate.observe(100);
+        assert_eq!(rate.blocks_per_second(), None);
+
+        time::advance(Duration::from_secs(60)).await;
+        rate.observe(110);
+        assert_eq!(rate.blocks_per_second(), Some(10.0 / 60.0));
+
+        // A restarted node reporting a lower number keeps the previous estimate
+        time::advance(Duration::from_secs(60)).await;
+        rate.observe(50);
+        assert_eq!(rate.blocks_per_second(), Some(10.0 / 60.0));
+
+        time::advance(Duration::from_secs(30)).await;
+        rate.observe(50);
+        assert_eq!(rate.blocks_per_second(), Some(0.0));
+    }
+}
@@ -0,0 +1,227 @@
+//! Minimal X.509 parsing for certificate expiry monitoring and pinning.
+//!
+//! Only the validity period and public key are needed, so instead of pulling in a
+//! full X.509 parser the DER structure is walked just far enough to reach them.
+
+use ring::digest;
+
+/// DER tag of a SEQUENCE.
+const TAG_SEQUENCE: u8 = 0x30;
+/// DER tag of the explicit `[0]` version field of a TBSCertificate.
+const TAG_VERSION: u8 = 0xa0;
+/// DER tag of a UTCTime.
+const TAG_UTC_TIME: u8 = 0x17;
+/// DER tag of a GeneralizedTime.
+const TAG_GENERALIZED_TIME: u8 = 0x18;
+
+/// Returns the earliest `notAfter` time among DER-encoded certificates, as a Unix timestamp.
+///
+/// Certificates that cannot be parsed are ignored.
+pub fn earliest_expiry<'a>(certs: impl IntoIterator<Item = &'a [u8]>) -> Option<i64> {
+    certs.into_iter().filter_map(not_after).min()
+}
+
+/// Returns the `notAfter` time of a DER-encoded certificate as a Unix timestamp.
+pub fn not_after(der: &[u8]) -> Option<i64> {
+    let (validity, _) = read_tlv(validity_onwards(der)?, TAG_SEQUENCE)?;
+    let not_after = skip_tlv(validity)?;
+    match *not_after.first()? {
+        TAG_UTC_TIME => parse_time(read_tlv(not_after, TAG_UTC_TIME)?.0, false),
+        TAG_GENERALIZED_TIME => parse_time(read_tlv(not_after, TAG_GENERALIZED_TIME)?.0, true),
+        _ => None,
+    }
+}
+
+/// Returns the hex-encoded SHA-256 fingerprints of a DER-encoded certificate and,
+/// if it can be parsed, of its DER-encoded `subjectPublicKeyInfo`.
+///
+/// The public key fingerprint stays the same when a certificate is renewed with
+/// the same key.
+pub fn fingerprints(der: &[u8]) -> Vec<String> {
+    std::iter::once(der)
+        .chain(public_key_info(der))
+        .map(|bytes| hex(digest::digest(&digest::SHA256, bytes).as_ref()))
+        .collect()
+}
+
+/// Returns the complete `subjectPublicKeyInfo` TLV of a DER-encoded certificate.
+fn public_key_info(der: &[u8]) -> Option<&[u8]> {
+    // validity and subject precede subjectPublicKeyInfo
+    let spki = skip_tlv(skip_tlv(validity_onwards(der)?)?)?;
+    if *spki.first()? != TAG_SEQUENCE {
+        return None;
+    }
+    let rest = skip_tlv(spki)?;
+    Some(&spki[..spki.len() - rest.len()])
+}
+
+/// Returns the TBSCertificate of a DER-encoded certificate, starting at `validity`.
+fn validity_onwards(der: &[u8]) -> Option<&[u8]> {
+    let (cert, _) = read_tlv(der, TAG_SEQUENCE)?;
+    let (mut tbs, _) = read_tlv(cert, TAG_SEQUENCE)?;
+
+    // version [0] is optional, then serialNumber, signature and issuer precede validity
+    if tbs.first() == Some(&TAG_VERSION) {
+        tbs = skip_tlv(tbs)?;
+    }
+    for _ in 0..3 {
+        tbs = skip_tlv(tbs)?;
+    }
+
+    Some(tbs)
+}
+
+/// Encodes bytes as lowercase hex.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Reads a TLV with the expected tag, returning its content and the remaining input.
+fn read_tlv(input: &[u8], tag: u8) -> Option<(&[u8], &[u8])> {
+    if *input.first()? != tag {
+        return None;
+    }
+    let (content, rest) = split_tlv(input)?;
+    Some((content, rest))
+}
+
+/// Skips a TLV of any tag, returning the remaining input.
+fn skip_tlv(input: &[u8]) -> Option<&[u8]> {
+    split_tlv(input).map(|(_, rest)| rest)
+}
+
+/// Splits a TLV into its content and the remaining input.
+fn split_tlv(input: &[u8]) -> Option<(&[u8], &[u8])> {
+    let first = *input.get(1)?;
+    let (length, header) = if first < 0x80 {
+        (first as usize, 2)
+    } else {
+        let bytes = (first & 0x7f) as usize;
+        if bytes == 0 || bytes > 4 {
+            return None;
+        }
+        let length = input
+            .get(2..2 + bytes)?
+            .iter()
+            .fold(0usize, |length, &b| (length << 8) | b as usize);
+        (length, 2 + bytes)
+    };
+
+    let content = input.get(header..header + length)?;
+    Some((content, &input[header + length..]))
+}
+
+/// Parses a UTCTime (`YYMMDDHHMMSSZ`) or GeneralizedTime (`YYYYMMDDHHMMSSZ`) into a
+/// Unix timestamp.
+fn parse_time(value: &[u8], generalized: bool) -> Option<i64> {
+    let value = std::str::from_utf8(value).ok()?.strip_suffix('Z')?;
+    let (year, rest) = if generalized {
+        (value.get(..4)?.parse::<i64>().ok()?, value.get(4..)?)
+    } else {
+        // RFC 5280: two-digit years 50-99 are 19xx, 00-49 are 20xx
+        let year = value.get(..2)?.parse::<i64>().ok()?;
+        (
+            if year >= 50 { 1900 + year } else { 2000 + year },
+            value.get(2..)?,
+        )
+    };
+    if rest.len() != 10 || !rest.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    let field = |i: usize| rest[i..i + 2].parse::<i64>().unwrap();
+    let (month, day) = (field(0), field(2));
+    let (hour, minute, second) = (field(4), field(6), field(8));
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    Some(days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+/// Returns the number of days since the Unix epoch for a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        days_from_civil, earliest_expiry, fingerprints, not_after, parse_time, public_key_info,
+    };
+
+    /// Encodes a DER TLV with a short-form or two-byte long-form length.
+    fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        if content.len() < 0x80 {
+            out.push(content.len() as u8);
+        } else {
+            out.extend([0x82, (content.len() >> 8) as u8, content.len() as u8]);
+        }
+        out.extend_from_slice(content);
+        out
+    }
+
+    /// Builds a certificate skeleton with the given `notAfter` time.
+    fn certificate(not_after: Vec<u8>) -> Vec<u8> {
+        let validity = [tlv(0x17, b"240101000000Z"), not_after].concat();
+        let tbs = [
+            tlv(0xa0, &tlv(0x02, &[2])),
+            tlv(0x02, &[1]),
+            tlv(0x30, &tlv(0x06, &[0x2a])),
+            tlv(0x30, &[0u8; 200]),
+            tlv(0x30, &validity),
+            tlv(0x30, &[]),
+            tlv(0x30, &tlv(0x03, &[0, 4, 2])),
+        ]
+        .concat();
+        tlv(0x30, &[tlv(0x30, &tbs), tlv(0x30, &[])].concat())
+    }
+
+    #[test]
+    fn converts_dates_to_unix_time() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        assert_eq!(parse_time(b"250614120000Z", false), Some(1749902400));
+        assert_eq!(parse_time(b"20500101000000Z", true), Some(2524608000));
+        assert_eq!(parse_time(b"2506141200Z", false), None);
+    }
+
+    #[test]
+    fn reads_not_after() {
+        let utc = certificate(tlv(0x17, b"250614120000Z"));
+        let generalized = certificate(tlv(0x18, b"20500101000000Z"));
+        assert_eq!(not_after(&utc), Some(1749902400));
+        assert_eq!(not_after(&generalized), Some(2524608000));
+        assert_eq!(not_after(&utc[..20]), None);
+
+        assert_eq!(
+            earliest_expiry([generalized.as_slice(), utc.as_slice()]),
+            Some(1749902400)
+        );
+    }
+
+    #[test]
+    fn fingerprints_certificate_and_public_key() {
+        let cert = certificate(tlv(0x17, b"250614120000Z"));
+        assert_eq!(
+            public_key_info(&cert),
+            Some(&[0x30, 5, 0x03, 3, 0, 4, 2][..])
+        );
+
+        let pins = fingerprints(&cert);
+        assert_eq!(pins.len(), 2);
+        assert!(pins.iter().all(|pin| pin.len() == 64));
+        // SHA-256 of the public key info above
+        assert_eq!(
+            pins[1],
+            "270c657833cb8390804fd5b9827f2790f54027ce8a87c895451b637ccbada879"
+        );
+        assert_eq!(fingerprints(&cert[..20]).len(), 1);
+    }
+}
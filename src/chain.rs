@@ -0,0 +1,750 @@
+//! Chain-specific RPC presets used by the connection monitor.
+//!
+//! Each preset knows which RPC methods report the finalized block and the sync state
+//! for its ecosystem, and how to parse their responses into a common [`ChainStatus`].
+
+use clap::ValueEnum;
+use jsonrpsee::core::ClientError;
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::core::params::ArrayParams;
+use jsonrpsee::rpc_params;
+use jsonrpsee::types::error::ErrorCode;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::sync::Mutex;
+
+/// Storage key of `Timestamp.Now`, i.e. `twox128("Timestamp") ++ twox128("Now")`.
+const TIMESTAMP_NOW_KEY: &str =
+    "0xf0c365c3cf59d671eb72da0e7a4113c49f1f0515f462cdcf84e0f1d6045dfcbb";
+
+/// RPC preset selecting which methods are used for checks.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChainType {
+    /// Substrate-based node (`chain_getFinalizedHead`, `system_syncState`, `system_health`).
+    #[default]
+    Substrate,
+    /// Ethereum-style JSON-RPC node (`eth_blockNumber`, `eth_syncing`, `net_peerCount`).
+    Ethereum,
+}
+
+/// Block and sync information reported by the node during a check.
+///
+/// Values whose RPC call failed are `None`, which only happens with a quorum.
+#[derive(Clone, Debug, Default)]
+pub struct ChainStatus {
+    /// Hash of the finalized head, if the preset reports one.
+    pub finalized_hash: Option<String>,
+    /// Number of the finalized block (latest block for Ethereum).
+    pub finalized_block_number: Option<u64>,
+    /// Number of blocks the node is behind the highest known block.
+    pub sync_lag_blocks: Option<u64>,
+    /// Number of peers the node is connected to.
+    pub peers: Option<u64>,
+    /// Number of blocks the best head is ahead of the finalized head, if fetched.
+    pub finality_gap_blocks: Option<u64>,
+    /// Spec version of the node's runtime at the best head, if fetched.
+    pub spec_version: Option<u64>,
+    /// Timestamp of the finalized (latest for Ethereum) block in Unix milliseconds, if fetched.
+    pub block_timestamp_ms: Option<u64>,
+}
+
+impl fmt::Display for ChainStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let value = |value: Option<u64>| value.map_or("unknown".to_string(), |v| v.to_string());
+        write!(
+            f,
+            "finalized block #{}, sync lag: {} blocks, peers: {}",
+            value(self.finalized_block_number),
+            value(self.sync_lag_blocks),
+            value(self.peers)
+        )
+    }
+}
+
+/// Sub-calls of a status check, each reporting one value of [`ChainStatus`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatusCall {
+    /// Finalized (Substrate) or latest (Ethereum) block number.
+    Block,
+    /// Sync state.
+    Sync,
+    /// Peer count.
+    Peers,
+}
+
+impl StatusCall {
+    /// All sub-calls, in the order they are made.
+    pub const ALL: [StatusCall; 3] = [StatusCall::Block, StatusCall::Sync, StatusCall::Peers];
+
+    /// Returns the name used in metric labels.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            StatusCall::Block => "block",
+            StatusCall::Sync => "sync",
+            StatusCall::Peers => "peers",
+        }
+    }
+}
+
+/// Descriptive attributes of the node, exposed as an info metric.
+#[derive(Clone, Debug)]
+pub struct NodeInfo {
+    /// Chain name (Substrate) or chain ID (Ethereum), `None` if not implemented by the node.
+    pub chain: Option<String>,
+    /// Node implementation version, `None` if not implemented by the node.
+    pub version: Option<String>,
+}
+
+/// Number of the last seen head, to skip `chain_getHeader` while it does not move.
+#[derive(Default)]
+pub struct HeadCache {
+    /// Hash and number of the last head.
+    last: Mutex<Option<(String, u64)>>,
+}
+
+impl HeadCache {
+    /// Returns the cached number of the block with the given hash, if it is the last one seen.
+    fn get(&self, hash: &str) -> Option<u64> {
+        match &*self.last.lock().unwrap() {
+            Some((last, number)) if last == hash => Some(*number),
+            _ => None,
+        }
+    }
+
+    /// Remembers the number of the head with the given hash.
+    fn store(&self, hash: &str, number: u64) {
+        *self.last.lock().unwrap() = Some((hash.to_owned(), number));
+    }
+}
+
+/// Substrate block header, reduced to the fields needed by the monitor.
+#[derive(Deserialize)]
+struct SubstrateHeader {
+    /// Hex-encoded block number.
+    number: String,
+}
+
+/// Substrate `state_getRuntimeVersion` response, reduced to the fields needed by the monitor.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SubstrateRuntimeVersion {
+    /// Version of the runtime specification, bumped by every runtime upgrade.
+    spec_version: u64,
+}
+
+/// Ethereum block, reduced to its timestamp.
+#[derive(Deserialize)]
+struct EthereumBlockTimestamp {
+    /// Hex-encoded Unix timestamp of the block, in seconds.
+    timestamp: String,
+}
+
+/// Ethereum block, reduced to the fields needed by the monitor.
+#[derive(Deserialize)]
+struct EthereumBlock {
+    /// Hex-encoded block number.
+    number: String,
+}
+
+/// Substrate `system_syncState` response.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SubstrateSyncState {
+    /// Best block imported by the node.
+    current_block: u64,
+    /// Highest block seen among peers.
+    highest_block: u64,
+}
+
+/// Substrate `system_health` response, reduced to the fields needed by the monitor.
+#[derive(Deserialize)]
+struct SubstrateHealth {
+    /// Number of connected peers.
+    peers: u64,
+}
+
+/// Ethereum `eth_syncing` response, which is `false` when not syncing.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum EthereumSyncing {
+    /// The node is fully synced.
+    NotSyncing(bool),
+    /// The node is syncing.
+    Syncing {
+        /// Hex-encoded best block imported by the node.
+        #[serde(rename = "currentBlock")]
+        current_block: String,
+        /// Hex-encoded highest block seen among peers.
+        #[serde(rename = "highestBlock")]
+        highest_block: String,
+    },
+}
+
+impl ChainType {
+    /// Fetches the finalized block, sync state and peer count using this preset's RPC methods.
+    ///
+    /// Without a `quorum`, every sub-call must succeed and the first failure ends the
+    /// check. With a quorum, all sub-calls are made and the check succeeds if at least
+    /// `quorum` of them did; otherwise the first failure is returned. With a `cache`,
+    /// the finalized block number is only looked up when the finalized head has moved
+    /// since the previous call.
+    ///
+    /// # Returns
+    ///
+    /// The check result and whether each sub-call that was made succeeded.
+    pub async fn fetch_status<C: ClientT>(
+        self,
+        client: &C,
+        cache: Option<&HeadCache>,
+        quorum: Option<usize>,
+    ) -> (Result<ChainStatus, FetchError>, Vec<(StatusCall, bool)>) {
+        let mut status = ChainStatus::default();
+        let mut calls = Vec::with_capacity(StatusCall::ALL.len());
+        let mut first_error = None;
+        for call in StatusCall::ALL {
+            let result = self.fetch_value(client, call, cache, &mut status).await;
+            calls.push((call, result.is_ok()));
+            if let Err(e) = result {
+                first_error.get_or_insert(e);
+                if quorum.is_none() {
+                    break;
+                }
+            }
+        }
+
+        let succeeded = calls.iter().filter(|(_, ok)| *ok).count();
+        let result = match first_error {
+            Some(e) if quorum.is_none_or(|quorum| succeeded < quorum) => Err(e),
+            _ => Ok(status),
+        };
+        (result, calls)
+    }
+
+    /// Makes a single sub-call of a status check, storing its value into `status`.
+    async fn fetch_value<C: ClientT>(
+        self,
+        client: &C,
+        call: StatusCall,
+        cache: Option<&HeadCache>,
+        status: &mut ChainStatus,
+    ) -> Result<(), FetchError> {
+        match (self, call) {
+            (ChainType::Substrate, StatusCall::Block) => {
+                let hash: String = request(client, "chain_getFinalizedHead", rpc_params![]).await?;
+                status.finalized_block_number = Some(header_number(client, &hash, cache).await?);
+                status.finalized_hash = Some(hash);
+            }
+            (ChainType::Substrate, StatusCall::Sync) => {
+                let sync: SubstrateSyncState =
+                    request(client, "system_syncState", rpc_params![]).await?;
+                status.sync_lag_blocks =
+                    Some(sync.highest_block.saturating_sub(sync.current_block));
+            }
+            (ChainType::Substrate, StatusCall::Peers) => {
+                let health: SubstrateHealth =
+                    request(client, "system_health", rpc_params![]).await?;
+                status.peers = Some(health.peers);
+            }
+            (ChainType::Ethereum, StatusCall::Block) => {
+                let number: String = request(client, "eth_blockNumber", rpc_params![]).await?;
+                status.finalized_block_number = Some(parse_hex_number(&number)?);
+            }
+            (ChainType::Ethereum, StatusCall::Sync) => {
+                let syncing: EthereumSyncing =
+                    request(client, "eth_syncing", rpc_params![]).await?;
+                let lag = match syncing {
+                    EthereumSyncing::NotSyncing(false) => 0,
+                    EthereumSyncing::NotSyncing(true) => {
+                        return Err(ClientError::Custom(
+                            "eth_syncing returned true without sync progress".to_string(),
+                        )
+                        .into());
+                    }
+                    EthereumSyncing::Syncing {
+                        current_block,
+                        highest_block,
+                    } => parse_hex_number(&highest_block)?
+                        .saturating_sub(parse_hex_number(&current_block)?),
+                };
+                status.sync_lag_blocks = Some(lag);
+            }
+            (ChainType::Ethereum, StatusCall::Peers) => {
+                let peer_count: String = request(client, "net_peerCount", rpc_params![]).await?;
+                status.peers = Some(parse_hex_number(&peer_count)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns how many blocks the best head is ahead of the finalized head.
+    ///
+    /// Fetches the best head for Substrate (`chain_getHead`), whose number is only
+    /// looked up when it moved if a `cache` is given, and the finalized block for
+    /// Ethereum, whose `status` reports the latest one. Returns `None` if `status`
+    /// has no block number to compare with.
+    pub async fn fetch_finality_gap<C: ClientT>(
+        self,
+        client: &C,
+        status: &ChainStatus,
+        cache: Option<&HeadCache>,
+    ) -> Result<Option<u64>, FetchError> {
+        let Some(reported) = status.finalized_block_number else {
+            return Ok(None);
+        };
+        let gap = match self {
+            ChainType::Substrate => {
+                let hash: String = request(client, "chain_getHead", rpc_params![]).await?;
+                header_number(client, &hash, cache)
+                    .await?
+                    .saturating_sub(reported)
+            }
+            ChainType::Ethereum => {
+                let block: Option<EthereumBlock> = request(
+                    client,
+                    "eth_getBlockByNumber",
+                    rpc_params!["finalized", false],
+                )
+                .await?;
+                let Some(block) = block else {
+                    return Err(ClientError::Custom(
+                        "eth_getBlockByNumber returned no finalized block".into(),
+                    )
+                    .into());
+                };
+                reported.saturating_sub(parse_hex_number(&block.number)?)
+            }
+        };
+
+        Ok(Some(gap))
+    }
+
+    /// Returns the timestamp of the block reported by `status`, in Unix milliseconds.
+    ///
+    /// Reads the `Timestamp.Now` storage item at the finalized head for Substrate,
+    /// set by the timestamp inherent of every block, and the block's `timestamp`
+    /// for Ethereum. Returns `None` if `status` has no block to look up or the
+    /// chain has no timestamp pallet.
+    pub async fn fetch_block_timestamp<C: ClientT>(
+        self,
+        client: &C,
+        status: &ChainStatus,
+    ) -> Result<Option<u64>, FetchError> {
+        match self {
+            ChainType::Substrate => {
+                let Some(hash) = &status.finalized_hash else {
+                    return Ok(None);
+                };
+                let value: Option<String> = request(
+                    client,
+                    "state_getStorage",
+                    rpc_params![TIMESTAMP_NOW_KEY, hash],
+                )
+                .await?;
+                value
+                    .map(|value| decode_u64_le(&value))
+                    .transpose()
+                    .map_err(Into::into)
+            }
+            ChainType::Ethereum => {
+                let Some(number) = status.finalized_block_number else {
+                    return Ok(None);
+                };
+                let block: Option<EthereumBlockTimestamp> = request(
+                    client,
+                    "eth_getBlockByNumber",
+                    rpc_params![format!("{number:#x}"), false],
+                )
+                .await?;
+                let Some(block) = block else {
+                    return Ok(None);
+                };
+                Ok(Some(
+                    parse_hex_number(&block.timestamp)?.saturating_mul(1000),
+                ))
+            }
+        }
+    }
+
+    /// Returns the spec version of the runtime at the best head.
+    ///
+    /// Only Substrate nodes have a runtime, so this is always `None` for Ethereum.
+    pub async fn fetch_spec_version<C: ClientT>(
+        self,
+        client: &C,
+    ) -> Result<Option<u64>, FetchError> {
+        match self {
+            ChainType::Substrate => {
+                let version: SubstrateRuntimeVersion =
+                    request(client, "state_getRuntimeVersion", rpc_params![]).await?;
+                Ok(Some(version.spec_version))
+            }
+            ChainType::Ethereum => Ok(None),
+        }
+    }
+
+    /// Queries the block and state at the head reported earlier in the same check.
+    ///
+    /// Fails if the node cannot serve the block or state queries at that head,
+    /// which a node that only tracks the latest head would not notice.
+    pub async fn verify_pinned_head<C: ClientT>(
+        self,
+        client: &C,
+        status: &ChainStatus,
+    ) -> Result<(), FetchError> {
+        match (self, &status.finalized_hash) {
+            (ChainType::Substrate, Some(hash)) => {
+                let block: Option<serde_json::Value> =
+                    request(client, "chain_getBlock", rpc_params![hash]).await?;
+                if block.is_none() {
+                    return Err(ClientError::Custom(format!(
+                        "chain_getBlock returned no block for finalized head {hash}"
+                    ))
+                    .into());
+                }
+                let _: serde_json::Value =
+                    request(client, "state_getRuntimeVersion", rpc_params![hash]).await?;
+            }
+            (ChainType::Substrate, None) => {}
+            (ChainType::Ethereum, _) => {
+                let Some(number) = status.finalized_block_number else {
+                    return Ok(());
+                };
+                let number = format!("{number:#x}");
+                let block: Option<serde_json::Value> =
+                    request(client, "eth_getBlockByNumber", rpc_params![&number, false]).await?;
+                if block.is_none() {
+                    return Err(ClientError::Custom(format!(
+                        "eth_getBlockByNumber returned no block for {number}"
+                    ))
+                    .into());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fetches the chain name or ID and the node version.
+    ///
+    /// Attributes whose method the node does not implement are `None` rather than
+    /// failing, as they are only used to enrich metrics.
+    pub async fn fetch_info<C: ClientT>(self, client: &C) -> Result<NodeInfo, FetchError> {
+        let (chain, version) = match self {
+            ChainType::Substrate => (
+                unless_unsupported(request(client, "system_chain", rpc_params![]).await)?,
+                unless_unsupported(request(client, "system_version", rpc_params![]).await)?,
+            ),
+            ChainType::Ethereum => (
+                unless_unsupported(request(client, "eth_chainId", rpc_params![]).await)?,
+                unless_unsupported(request(client, "web3_clientVersion", rpc_params![]).await)?,
+            ),
+        };
+
+        Ok(NodeInfo { chain, version })
+    }
+
+    /// Returns whether the node keeps the state of all blocks rather than pruning it.
+    ///
+    /// Queries the state at block 1, which pruned nodes have long discarded: an error
+    /// response means the node is pruned, other failures are returned as errors.
+    pub async fn fetch_is_archive<C: ClientT>(self, client: &C) -> Result<bool, FetchError> {
+        let result: Result<serde_json::Value, _> = match self {
+            ChainType::Substrate => {
+                let hash: Option<String> =
+                    request(client, "chain_getBlockHash", rpc_params![1]).await?;
+                let Some(hash) = hash else {
+                    return Err(ClientError::Custom(
+                        "chain_getBlockHash returned no block 1".into(),
+                    )
+                    .into());
+                };
+                request(client, "state_getRuntimeVersion", rpc_params![hash]).await
+            }
+            ChainType::Ethereum => {
+                request(
+                    client,
+                    "eth_getBalance",
+                    rpc_params!["0x0000000000000000000000000000000000000000", "0x1"],
+                )
+                .await
+            }
+        };
+
+        match result {
+            Ok(_) => Ok(true),
+            Err(FetchError::Client(ClientError::Call(_))) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Fetches fields identifying the chain, which must not change between checks.
+    ///
+    /// Returns `(field, value)` pairs, e.g. the chain name and genesis hash for Substrate.
+    pub async fn fetch_identity<C: ClientT>(
+        self,
+        client: &C,
+    ) -> Result<Vec<(&'static str, String)>, FetchError> {
+        match self {
+            ChainType::Substrate => {
+                let chain: String = request(client, "system_chain", rpc_params![]).await?;
+                let genesis_hash: String =
+                    request(client, "chain_getBlockHash", rpc_params![0]).await?;
+                Ok(vec![("chain", chain), ("genesis_hash", genesis_hash)])
+            }
+            ChainType::Ethereum => {
+                let chain_id: String = request(client, "eth_chainId", rpc_params![]).await?;
+                Ok(vec![("chain_id", chain_id)])
+            }
+        }
+    }
+}
+
+/// Returns the number of the Substrate block with the given hash, from `cache` if
+/// it is the last one seen.
+async fn header_number<C: ClientT>(
+    client: &C,
+    hash: &str,
+    cache: Option<&HeadCache>,
+) -> Result<u64, FetchError> {
+    if let Some(number) = cache.and_then(|cache| cache.get(hash)) {
+        return Ok(number);
+    }
+    let header: SubstrateHeader = request(client, "chain_getHeader", rpc_params![hash]).await?;
+    let number = parse_hex_number(&header.number)?;
+    if let Some(cache) = cache {
+        cache.store(hash, number);
+    }
+
+    Ok(number)
+}
+
+impl ChainType {
+    /// Makes a lightweight call without side effects, keeping an idle connection
+    /// open through intermediaries with an idle timeout.
+    pub async fn keepalive<C: ClientT>(self, client: &C) -> Result<(), FetchError> {
+        let method = match self {
+            ChainType::Substrate => "system_name",
+            ChainType::Ethereum => "web3_clientVersion",
+        };
+        request::<String, _>(client, method, rpc_params![]).await?;
+        Ok(())
+    }
+}
+
+/// Sends an RPC request and deserializes the result into `T`.
+///
+/// The result is first received as raw JSON, so that a response of an unexpected
+/// shape can be reported together with the value the node actually returned.
+async fn request<T: DeserializeOwned, C: ClientT>(
+    client: &C,
+    method: &'static str,
+    params: ArrayParams,
+) -> Result<T, FetchError> {
+    let raw: serde_json::Value = client.request(method, params).await?;
+    T::deserialize(&raw).map_err(|source| FetchError::Deserialization {
+        method,
+        target: short_type_name::<T>(),
+        raw,
+        source,
+    })
+}
+
+/// Returns the name of `T` without its module path, e.g. `String`.
+///
+/// Generic wrappers are skipped, so `Option<EthereumBlock>` is named after the
+/// wrapped `EthereumBlock`.
+fn short_type_name<T>() -> &'static str {
+    let mut name = std::any::type_name::<T>();
+    while let Some((_, inner)) = name.split_once('<') {
+        name = inner.strip_suffix('>').unwrap_or(inner);
+    }
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+/// Error returned when fetching information from the node fails.
+#[derive(Debug)]
+pub enum FetchError {
+    /// The RPC request failed or the response could not be interpreted.
+    Client(ClientError),
+    /// The response was valid JSON-RPC but did not match the expected type.
+    Deserialization {
+        /// RPC method whose response was invalid.
+        method: &'static str,
+        /// Name of the type the response was deserialized into.
+        target: &'static str,
+        /// The response as returned by the node.
+        raw: serde_json::Value,
+        /// The deserialization error.
+        source: serde_json::Error,
+    },
+}
+
+impl From<ClientError> for FetchError {
+    fn from(error: ClientError) -> Self {
+        FetchError::Client(error)
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::Client(error) => error.fmt(f),
+            FetchError::Deserialization { method, target, .. } => {
+                write!(f, "Unexpected {method} response, expected {target}")
+            }
+        }
+    }
+}
+
+impl Error for FetchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            FetchError::Client(error) => error.source(),
+            FetchError::Deserialization { source, .. } => Some(source),
+        }
+    }
+}
+
+/// Returns whether the error is a JSON-RPC level error rather than a transport failure.
+///
+/// This covers error responses (e.g. method not found) as well as responses that
+/// could not be parsed; in both cases the node did answer over the connection.
+pub fn is_rpc_error(error: &FetchError) -> bool {
+    match error {
+        FetchError::Client(error) => matches!(
+            error,
+            ClientError::Call(_) | ClientError::ParseError(_) | ClientError::Custom(_)
+        ),
+        FetchError::Deserialization { .. } => true,
+    }
+}
+
+/// Turns a method-not-found error response into `None`, for optional methods.
+fn unless_unsupported<T>(result: Result<T, FetchError>) -> Result<Option<T>, FetchError> {
+    match result {
+        Err(FetchError::Client(ClientError::Call(error)))
+            if error.code() == ErrorCode::MethodNotFound.code() =>
+        {
+            Ok(None)
+        }
+        result => result.map(Some),
+    }
+}
+
+/// Parses a `0x`-prefixed hex-encoded number, e.g. a block number or peer count.
+fn parse_hex_number(value: &str) -> Result<u64, ClientError> {
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    u64::from_str_radix(digits, 16)
+        .map_err(|e| ClientError::Custom(format!("Invalid hex number '{value}': {e}")))
+}
+
+/// Decodes a hex-encoded SCALE `u64`, i.e. eight little-endian bytes.
+fn decode_u64_le(value: &str) -> Result<u64, ClientError> {
+    let invalid = || ClientError::Custom(format!("Invalid SCALE u64 '{value}'"));
+    let digits = value.strip_prefix("0x").unwrap_or(value);
+    if digits.len() != 16 {
+        return Err(invalid());
+    }
+    let be = u64::from_str_radix(digits, 16).map_err(|_| invalid())?;
+    Ok(be.swap_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        ChainStatus, FetchError, HeadCache, SubstrateHeader, decode_u64_le, is_rpc_error,
+        parse_hex_number, short_type_name, unless_unsupported,
+    };
+    use jsonrpsee::core::ClientError;
+    use jsonrpsee::types::ErrorObject;
+    use jsonrpsee::types::error::ErrorCode;
+
+    #[test]
+    fn parses_hex_block_numbers() {
+        assert_eq!(parse_hex_number("0x1b4").unwrap(), 436);
+        assert_eq!(parse_hex_number("0x0").unwrap(), 0);
+        assert!(parse_hex_number("0xzz").is_err());
+    }
+
+    #[test]
+    fn decodes_scale_timestamps() {
+        // 1_760_443_200_000 ms, little-endian
+        assert_eq!(
+            decode_u64_le("0x007297e299010000").unwrap(),
+            1_760_443_200_000
+        );
+        assert!(decode_u64_le("0x00e3ece2").is_err());
+        assert!(decode_u64_le("0x00e3ece29901zzzz").is_err());
+    }
+
+    #[test]
+    fn formats_partial_status() {
+        let status = ChainStatus {
+            finalized_block_number: Some(436),
+            sync_lag_blocks: Some(0),
+            ..ChainStatus::default()
+        };
+        assert_eq!(
+            status.to_string(),
+            "finalized block #436, sync lag: 0 blocks, peers: unknown"
+        );
+    }
+
+    #[test]
+    fn caches_only_the_last_head() {
+        let cache = HeadCache::default();
+        assert_eq!(cache.get("0xaa"), None);
+        cache.store("0xaa", 10);
+        assert_eq!(cache.get("0xaa"), Some(10));
+        cache.store("0xbb", 11);
+        assert_eq!(cache.get("0xaa"), None);
+        assert_eq!(cache.get("0xbb"), Some(11));
+    }
+
+    #[test]
+    fn distinguishes_rpc_errors_from_transport_failures() {
+        let call = ClientError::Call(ErrorObject::from(ErrorCode::MethodNotFound));
+        assert!(is_rpc_error(&call.into()));
+        assert!(!is_rpc_error(&ClientError::RequestTimeout.into()));
+    }
+
+    #[test]
+    fn skips_unsupported_methods() {
+        let unsupported = ClientError::Call(ErrorObject::from(ErrorCode::MethodNotFound));
+        assert!(matches!(
+            unless_unsupported::<String>(Err(unsupported.into())),
+            Ok(None)
+        ));
+        let internal = ClientError::Call(ErrorObject::from(ErrorCode::InternalError));
+        assert!(unless_unsupported::<String>(Err(internal.into())).is_err());
+        assert!(matches!(unless_unsupported(Ok(1)), Ok(Some(1))));
+    }
+
+    #[test]
+    fn names_deserialization_target() {
+        assert_eq!(short_type_name::<String>(), "String");
+        assert_eq!(short_type_name::<SubstrateHeader>(), "SubstrateHeader");
+        assert_eq!(short_type_name::<Option<String>>(), "String");
+        assert_eq!(
+            short_type_name::<Vec<Option<SubstrateHeader>>>(),
+            "SubstrateHeader"
+        );
+
+        let raw = serde_json::json!(42);
+        let error = FetchError::Deserialization {
+            method: "chain_getFinalizedHead",
+            target: short_type_name::<String>(),
+            source: serde_json::from_value::<String>(raw.clone()).unwrap_err(),
+            raw,
+        };
+        assert_eq!(
+            error.to_string(),
+            "Unexpected chain_getFinalizedHead response, expected String"
+        );
+        assert!(is_rpc_error(&error));
+    }
+}
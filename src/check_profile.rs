@@ -0,0 +1,179 @@
+//! Check intervals depending on the time of day, e.g. checking less often during
+//! known maintenance windows and more often during business hours.
+//!
+//! Windows are given with a fixed UTC offset. Daylight saving time is not
+//! followed, as that would need a timezone database.
+
+use anyhow::{Context, Result, bail};
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Minutes in a day.
+const DAY_MINUTES: u32 = 24 * 60;
+
+/// A check interval used during a daily time window, parsed from
+/// `HH:MM-HH:MM[±HH:MM]=SECONDS`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(into = "String")]
+pub struct CheckProfile {
+    /// Start of the window in minutes after local midnight, inclusive.
+    start: u32,
+    /// End of the window in minutes after local midnight, exclusive.
+    end: u32,
+    /// Offset of the window's local time from UTC, in minutes.
+    utc_offset: i32,
+    /// Interval between checks during the window.
+    pub interval: Duration,
+}
+
+impl FromStr for CheckProfile {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let Some((window, interval)) = spec.split_once('=') else {
+            bail!("Expected HH:MM-HH:MM[±HH:MM]=SECONDS, got '{spec}'");
+        };
+        let (Some(start), Some("-"), Some(end), Some(offset)) = (
+            window.get(..5),
+            window.get(5..6),
+            window.get(6..11),
+            window.get(11..),
+        ) else {
+            bail!("Expected HH:MM-HH:MM[±HH:MM]=SECONDS, got '{spec}'");
+        };
+        let utc_offset = match offset.split_at_checked(1) {
+            None => 0,
+            Some(("+", offset)) => parse_time(offset)? as i32,
+            Some(("-", offset)) => -(parse_time(offset)? as i32),
+            Some(_) => bail!("Invalid UTC offset '{offset}'"),
+        };
+        let (start, end) = (parse_time(start)?, parse_time(end)?);
+        if start == end {
+            bail!("Empty time window '{window}'");
+        }
+        let interval: u64 = interval
+            .parse()
+            .with_context(|| format!("Invalid interval '{interval}'"))?;
+        if interval == 0 {
+            bail!("Interval must be at least 1 second");
+        }
+
+        Ok(Self {
+            start,
+            end,
+            utc_offset,
+            interval: Duration::from_secs(interval),
+        })
+    }
+}
+
+impl fmt::Display for CheckProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let hm = |minutes: u32| format!("{:02}:{:02}", minutes / 60, minutes % 60);
+        let sign = if self.utc_offset < 0 { '-' } else { '+' };
+        write!(
+            f,
+            "{}-{}{sign}{}={}",
+            hm(self.start),
+            hm(self.end),
+            hm(self.utc_offset.unsigned_abs()),
+            self.interval.as_secs()
+        )
+    }
+}
+
+impl From<CheckProfile> for String {
+    fn from(profile: CheckProfile) -> Self {
+        profile.to_string()
+    }
+}
+
+impl CheckProfile {
+    /// Returns whether `now` falls into the window.
+    ///
+    /// Windows ending before they start span midnight, e.g. `22:00-06:00`.
+    fn contains(&self, now: SystemTime) -> bool {
+        let utc_minutes = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / 60;
+        let minute = (utc_minutes as i64 + i64::from(self.utc_offset))
+            .rem_euclid(i64::from(DAY_MINUTES)) as u32;
+        if self.start < self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        }
+    }
+}
+
+/// Returns the first of `profiles` whose window contains `now`, if any.
+pub fn active(profiles: &[CheckProfile], now: SystemTime) -> Option<&CheckProfile> {
+    profiles.iter().find(|profile| profile.contains(now))
+}
+
+/// Parses `HH:MM` into minutes after midnight.
+fn parse_time(time: &str) -> Result<u32> {
+    let parsed = time
+        .split_once(':')
+        .filter(|(hours, minutes)| hours.len() == 2 && minutes.len() == 2)
+        .and_then(|(hours, minutes)| {
+            Some((hours.parse::<u32>().ok()?, minutes.parse::<u32>().ok()?))
+        });
+    match parsed {
+        Some((hours, minutes)) if hours < 24 && minutes < 60 => Ok(hours * 60 + minutes),
+        _ => bail!("Invalid time '{time}', expected HH:MM"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CheckProfile, active};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    /// Returns the time at `hours:minutes` UTC on the first day of the epoch.
+    fn utc(hours: u64, minutes: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(hours * 3600 + minutes * 60)
+    }
+
+    #[test]
+    fn parses_profile_spec() {
+        let profile: CheckProfile = "09:00-17:30+02:00=10".parse().unwrap();
+        assert_eq!((profile.start, profile.end), (540, 1050));
+        assert_eq!(profile.utc_offset, 120);
+        assert_eq!(profile.interval, Duration::from_secs(10));
+        assert_eq!(profile.to_string(), "09:00-17:30+02:00=10");
+
+        let profile: CheckProfile = "22:00-06:00-05:30=300".parse().unwrap();
+        assert_eq!(profile.utc_offset, -330);
+        let profile: CheckProfile = "22:00-06:00=300".parse().unwrap();
+        assert_eq!(profile.utc_offset, 0);
+
+        for spec in [
+            "09:00-17:00",
+            "9:00-17:00=10",
+            "09:00-24:00=10",
+            "09:00-09:00=10",
+            "09:00-17:00*02:00=10",
+            "09:00-17:00=0",
+            "09:00-17:00=ten",
+        ] {
+            assert!(spec.parse::<CheckProfile>().is_err(), "{spec}");
+        }
+    }
+
+    #[test]
+    fn selects_first_matching_window() {
+        let profiles: Vec<CheckProfile> = ["09:00-17:00+02:00=10", "22:00-06:00=300"]
+            .iter()
+            .map(|spec| spec.parse().unwrap())
+            .collect();
+        let interval = |now| active(&profiles, now).map(|profile| profile.interval.as_secs());
+
+        assert_eq!(interval(utc(6, 59)), None);
+        assert_eq!(interval(utc(7, 0)), Some(10));
+        assert_eq!(interval(utc(14, 59)), Some(10));
+        assert_eq!(interval(utc(15, 0)), None);
+        assert_eq!(interval(utc(23, 0)), Some(300));
+        assert_eq!(interval(utc(5, 59)), Some(300));
+    }
+}
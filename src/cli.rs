@@ -0,0 +1,1050 @@
+//! Command line interface.
+//!
+//! The daemon behavior lives in the `monitor` subcommand, which is also used when
+//! no subcommand is given, so existing invocations keep working.
+
+use crate::alert_template::AlertTemplate;
+use crate::chain::ChainType;
+use crate::check_profile::CheckProfile;
+use crate::custom_gauge::CustomGauge;
+use crate::rpc_version::RpcVersion;
+use anyhow::{Context, Result, bail};
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use serde::Serialize;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// Command line arguments
+#[derive(Parser)]
+#[command(version, about, long_about = None)]
+pub struct Cli {
+    /// Subcommand to run.
+    #[command(subcommand)]
+    pub command: Command,
+
+    /// Enable verbose logging.
+    ///
+    /// When set, changes log level from INFO to DEBUG.
+    #[arg(short, long, global = true, default_value_t = false)]
+    pub verbose: bool,
+
+    /// Format of log output.
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Full)]
+    pub log_format: LogFormat,
+}
+
+/// Log output formats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Default human-readable format with all event fields.
+    Full,
+    /// Shorter single-line format, e.g. for journald.
+    Compact,
+}
+
+/// How far a check goes before it counts as successful.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum CheckMode {
+    /// Full check with the `--chain-type` RPC calls.
+    #[default]
+    Rpc,
+    /// TCP connect, TLS handshake and WebSocket upgrade, without any RPC call.
+    Upgrade,
+    /// TCP connect only.
+    Tcp,
+}
+
+impl CheckMode {
+    /// Returns the name of the mode, as used in metric labels.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CheckMode::Rpc => "rpc",
+            CheckMode::Upgrade => "upgrade",
+            CheckMode::Tcp => "tcp",
+        }
+    }
+}
+
+/// Available subcommands.
+#[derive(Subcommand)]
+pub enum Command {
+    /// Continuously monitor the node and expose metrics (default).
+    Monitor(Box<MonitorArgs>),
+    /// Run a single check and exit with a Nagios-compatible status code.
+    Check(Box<CheckArgs>),
+    /// Print version information and exit.
+    Version,
+}
+
+/// Arguments shared by all subcommands that check the node.
+#[derive(Args, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct CheckArgs {
+    /// WebSocket URL of the Substrate node to monitor.
+    ///
+    /// This should be a valid WebSocket endpoint (ws:// or wss://). Can be omitted
+    /// when the URL is read from `--monitor-url-file` or `--monitor-url-env`.
+    #[arg(
+        value_name = "NODE_URL",
+        required_unless_present_any = ["monitor_url_file", "monitor_url_env"]
+    )]
+    #[serde(skip)]
+    pub url: Option<String>,
+
+    /// File to read the node URL from, instead of passing it as an argument.
+    ///
+    /// Keeps credentials-bearing URLs out of process listings. Surrounding
+    /// whitespace, such as a trailing newline, is ignored.
+    #[arg(long, conflicts_with_all = ["url", "monitor_url_env"])]
+    pub monitor_url_file: Option<PathBuf>,
+
+    /// Environment variable to read the node URL from, instead of passing it as an argument.
+    #[arg(long, conflicts_with = "url")]
+    pub monitor_url_env: Option<String>,
+
+    /// Node URL resolved from the argument, file or environment variable.
+    ///
+    /// Set by [`CheckArgs::resolve_node_url`] after parsing.
+    #[arg(skip)]
+    pub node_url: String,
+
+    /// Fallback WebSocket URL of the same node, tried if the primary one fails.
+    ///
+    /// Can be repeated; fallbacks are tried in the given order and the check
+    /// succeeds if any URL works.
+    #[arg(long)]
+    pub fallback_url: Vec<String>,
+
+    /// Timeout for establishing WebSocket connection in seconds.
+    ///
+    /// If the connection cannot be established within this time, it's marked as failed.
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+    pub monitor_connection_timeout: u64,
+
+    /// Timeout for RPC requests in seconds.
+    ///
+    /// After connection is established, this timeout applies to individual RPC calls.
+    #[arg(long, default_value_t = 5, value_parser = clap::value_parser!(u64).range(1..))]
+    pub monitor_request_timeout: u64,
+
+    /// WebSocket subprotocol to request during the handshake.
+    ///
+    /// Sent as the `Sec-WebSocket-Protocol` header, for gateways that require one.
+    #[arg(long)]
+    pub ws_subprotocol: Option<String>,
+
+    /// PEM file with the TLS client certificate chain for mutual TLS.
+    ///
+    /// Must be provided together with `--tls-client-key`.
+    #[arg(long, requires = "tls_client_key")]
+    pub tls_client_cert: Option<PathBuf>,
+
+    /// PEM file with the private key of the TLS client certificate.
+    #[arg(long, requires = "tls_client_cert")]
+    pub tls_client_key: Option<PathBuf>,
+
+    /// Server name (SNI) sent during the TLS handshake instead of the URL's host.
+    ///
+    /// For endpoints reached via an IP or internal name that present a
+    /// certificate for another name. The certificate is validated against this
+    /// name. Applies to `wss://` connections, not to the HTTP fallback.
+    #[arg(long)]
+    pub tls_sni: Option<String>,
+
+    /// SHA-256 fingerprint (hex) the server's TLS certificate chain must match (repeatable).
+    ///
+    /// Matches the fingerprint of any certificate in the chain or of its public
+    /// key info, so pinning a key survives certificate renewals. Pass several pins
+    /// during a rotation. A chain matching none fails the check with reason
+    /// `cert_pin_mismatch`. Ignored for `ws://` URLs.
+    #[arg(long = "tls-pin", value_parser = fingerprint)]
+    pub tls_pins: Vec<String>,
+
+    /// File holding a bearer token sent in the `Authorization` header.
+    ///
+    /// Re-read before every connection, so a token rotated by e.g. a sidecar is
+    /// used without a restart. Takes precedence over credentials in the URL. A
+    /// missing or empty file fails the check with reason `auth_config`.
+    #[arg(long)]
+    pub auth_token_file: Option<PathBuf>,
+
+    /// RPC preset used for checks.
+    ///
+    /// Selects the methods used to fetch the finalized block number and sync state.
+    #[arg(long, value_enum, default_value_t = ChainType::Substrate)]
+    pub chain_type: ChainType,
+
+    /// How far a check goes: `rpc` (full check), `upgrade` or `tcp`.
+    ///
+    /// `upgrade` only establishes the WebSocket connection and `tcp` only opens a
+    /// TCP connection, without any RPC call. They detect hard-down endpoints at
+    /// minimal cost, e.g. against gateways billing or throttling per call.
+    #[arg(long, value_enum, default_value_t = CheckMode::Rpc)]
+    pub check_mode: CheckMode,
+
+    /// JSON-RPC version of the requests sent to the node.
+    ///
+    /// Version 1.0 is for legacy gateways rejecting 2.0 requests: requests are
+    /// sent without the `jsonrpc` member and 1.0-style responses are accepted.
+    /// Checks over `--allow-http-fallback` always use 2.0.
+    #[arg(long, value_enum, default_value_t = RpcVersion::V2)]
+    pub rpc_version: RpcVersion,
+
+    /// Minimum number of peers the node must be connected to.
+    ///
+    /// A check where the node reports fewer peers fails with reason `low_peers`,
+    /// even though the RPC calls succeeded. When unset, the peer count is only
+    /// exposed as a metric.
+    #[arg(long)]
+    pub min_peers: Option<u64>,
+
+    /// Minimum spec version of the node's runtime, for catching missed runtime upgrades.
+    ///
+    /// Substrate only: every check also calls `state_getRuntimeVersion`, and a
+    /// node whose runtime has a lower `specVersion` fails with reason
+    /// `outdated_runtime`, even though the other RPC calls succeeded.
+    #[arg(long)]
+    pub min_spec_version: Option<u64>,
+
+    /// Also query the block and state at the head returned earlier in the check.
+    ///
+    /// Fails the check if the node cannot serve them, validating that it answers
+    /// state queries and not just returns a head hash. Uses `chain_getBlock` and
+    /// `state_getRuntimeVersion` for Substrate, `eth_getBlockByNumber` for Ethereum.
+    #[arg(long, default_value_t = false)]
+    pub pinned_head_check: bool,
+
+    /// Number of the block, sync and peer RPC calls that must succeed (1 to 3).
+    ///
+    /// By default all three are required and the first failure fails the check.
+    /// With a quorum, all of them are made and the check succeeds if at least this
+    /// many did, for nodes where some optional methods are disabled. Results per
+    /// call are counted in `rpc_call_count`.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..=3))]
+    pub rpc_quorum: Option<u64>,
+
+    /// Count JSON-RPC error responses as successful checks.
+    ///
+    /// Only transport and connection failures count as failures, e.g. when
+    /// monitoring a generic gateway that does not expose the preset's methods.
+    #[arg(long, default_value_t = false)]
+    pub ignore_rpc_errors: bool,
+
+    /// Fall back to HTTP JSON-RPC when the WebSocket upgrade is rejected.
+    ///
+    /// If the server answers the upgrade request with an HTTP error, e.g. behind a
+    /// proxy blocking WebSocket traffic, the check is retried over `http://` or
+    /// `https://` on the same host and path. Connection timings, the TLS
+    /// certificate and the TLS client certificate do not apply to such checks.
+    #[arg(long, default_value_t = false)]
+    pub allow_http_fallback: bool,
+}
+
+impl CheckArgs {
+    /// Resolves the node URL from the argument, file or environment variable
+    /// into [`CheckArgs::node_url`].
+    pub fn resolve_node_url(&mut self) -> Result<()> {
+        self.node_url = if let Some(path) = &self.monitor_url_file {
+            std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read node URL from '{}'", path.display()))?
+                .trim()
+                .to_string()
+        } else if let Some(name) = &self.monitor_url_env {
+            std::env::var(name)
+                .with_context(|| {
+                    format!("Failed to read node URL from environment variable '{name}'")
+                })?
+                .trim()
+                .to_string()
+        } else {
+            self.url.clone().unwrap_or_default()
+        };
+
+        Ok(())
+    }
+
+    /// Removes fallback URLs equal to the node URL or to an earlier fallback,
+    /// keeping the first occurrence of each.
+    ///
+    /// Templated configurations easily repeat a URL, which would only retry the
+    /// same URL within a check.
+    ///
+    /// # Returns
+    ///
+    /// * The removed duplicates, in their original order
+    pub fn dedup_urls(&mut self) -> Vec<String> {
+        let mut seen = vec![self.node_url.clone()];
+        let mut duplicates = Vec::new();
+        self.fallback_url.retain(|url| {
+            if seen.contains(url) {
+                duplicates.push(url.clone());
+                false
+            } else {
+                seen.push(url.clone());
+                true
+            }
+        });
+
+        duplicates
+    }
+}
+
+/// Arguments of the `monitor` subcommand.
+#[derive(Args, Clone, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct MonitorArgs {
+    /// Node URLs, timeouts and RPC preset.
+    #[command(flatten)]
+    #[serde(flatten)]
+    pub check: CheckArgs,
+
+    /// Interval between connection checks in seconds.
+    ///
+    /// The monitor will attempt to connect to the node at this interval. Must be
+    /// at least 1, as a zero interval would make the monitor busy-loop.
+    #[arg(long, default_value_t = 60, value_parser = clap::value_parser!(u64).range(1..))]
+    pub monitor_interval: u64,
+
+    /// Check interval used during a daily time window, as `HH:MM-HH:MM[±HH:MM]=SECONDS`.
+    ///
+    /// Overrides `--monitor-interval` between the two times, given in UTC or with
+    /// the UTC offset, e.g. `01:00-03:00+02:00=600` to check less often during a
+    /// nightly maintenance window. Windows ending before they start span
+    /// midnight. The first matching window applies; the active one is evaluated
+    /// before every check. Can be repeated.
+    #[arg(long, value_name = "HH:MM-HH:MM[±HH:MM]=SECONDS")]
+    pub check_profile: Vec<CheckProfile>,
+
+    /// Delay the next check while the gateway rate limits the monitor.
+    ///
+    /// After a check failing with reason `rate_limited`, e.g. because the
+    /// WebSocket upgrade was answered with HTTP status 429, the next check is
+    /// delayed by twice the interval, doubling with every further throttled
+    /// check up to 16 times the interval. The first check that is not throttled
+    /// restores the normal schedule.
+    #[arg(long, default_value_t = false)]
+    pub rate_limit_backoff: bool,
+
+    /// Delay the next check as long as a rejected WebSocket upgrade's
+    /// `Retry-After` header asks.
+    ///
+    /// Applies once, after which the normal schedule resumes. Only delays given
+    /// in seconds are understood, and at most one hour is waited.
+    #[arg(long, default_value_t = false)]
+    pub honor_retry_after: bool,
+
+    /// Keep the WebSocket connection open between checks.
+    ///
+    /// By default a new connection is established for every check. In persistent
+    /// mode the connection is reused and only re-established after it fails.
+    /// Requires the `rpc` check mode.
+    #[arg(long, default_value_t = false)]
+    pub persistent: bool,
+
+    /// Maximum age of the persistent connection in seconds, zero to disable.
+    ///
+    /// Once the connection is older, it is closed before the next check and a new
+    /// one is established, so a DNS load balancer can route to another backend.
+    #[arg(long, default_value_t = 0, requires = "persistent")]
+    pub connection_max_age: u64,
+
+    /// Seconds without a check after which a keepalive call is made on the
+    /// persistent connection.
+    ///
+    /// Keeps NAT gateways and proxies from reaping the connection as idle when
+    /// the check interval exceeds their idle timeout. The call (`system_name` or
+    /// `web3_clientVersion`) is not counted as a check; if it fails, the
+    /// connection is dropped and re-established by the next check.
+    #[arg(long, requires = "persistent", value_parser = clap::value_parser!(u64).range(1..))]
+    pub idle_keepalive: Option<u64>,
+
+    /// Detect unexpected changes of the node's chain identity between checks.
+    ///
+    /// Fetches fields that should never change, like the chain name and genesis
+    /// hash, on every successful check and counts changes in `unexpected_change_total`.
+    /// Catches silent backend swaps behind a load balancer.
+    #[arg(long, default_value_t = false)]
+    pub detect_response_changes: bool,
+
+    /// Look up the finalized block number only when the finalized head has moved.
+    ///
+    /// Substrate only: skips the `chain_getHeader` call while `chain_getFinalizedHead`
+    /// returns the same hash as in the previous check, reducing the load on
+    /// rate-limited gateways when checks are more frequent than finalization.
+    #[arg(long, default_value_t = false)]
+    pub cache_finalized_number: bool,
+
+    /// Also fetch the best head and expose its distance to the finalized head.
+    ///
+    /// A growing `head_finality_gap_blocks` reveals a finality stall while blocks
+    /// are still produced. With `--cache-finalized-number`, the best head's number
+    /// is likewise only looked up when it moved.
+    #[arg(long, default_value_t = false)]
+    pub finality_gap: bool,
+
+    /// Also fetch the timestamp of the finalized block and expose its age.
+    ///
+    /// `finalized_block_age_seconds` measures staleness in wall-clock terms. Uses
+    /// the `Timestamp.Now` storage item for Substrate and the block's `timestamp`
+    /// for Ethereum (the latest block); chains without timestamps expose no age.
+    #[arg(long, default_value_t = false)]
+    pub block_age: bool,
+
+    /// Expose the spec version of the node's runtime.
+    ///
+    /// Substrate only: every successful check also calls `state_getRuntimeVersion`
+    /// and sets `runtime_spec_version`, to see when a node picks up a runtime
+    /// upgrade. Implied by `--min-spec-version`.
+    #[arg(long, default_value_t = false)]
+    pub runtime_version: bool,
+
+    /// Consecutive failed checks required to mark the endpoint down.
+    ///
+    /// Prevents a single transient failure from flipping `endpoint_up` to 0.
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u64).range(1..))]
+    pub failure_threshold: u64,
+
+    /// Consecutive successful checks required to mark the endpoint up.
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    pub success_threshold: u64,
+
+    /// Total check duration in seconds above which a successful check counts as degraded.
+    ///
+    /// While the endpoint is up and its last check was degraded, `endpoint_state`
+    /// reports 2 instead of 1, and `degraded_checks_total` counts such checks.
+    /// Disabled when not set.
+    #[arg(long)]
+    pub degraded_latency_threshold: Option<f64>,
+
+    /// Seconds after startup during which `/readyz` reports "starting".
+    ///
+    /// Until the grace period ends or the first check completes, readiness is
+    /// reported as starting instead of not ready. Zero disables the grace period.
+    #[arg(long, default_value_t = 0)]
+    pub startup_grace: u64,
+
+    /// Report ready (200) instead of not ready (503) while starting.
+    #[arg(long, default_value_t = false)]
+    pub startup_grace_ready: bool,
+
+    /// Report ready only once the node has synced, not merely answered the checks.
+    ///
+    /// A syncing node answers RPC calls but cannot serve traffic yet, so while the
+    /// sync lag of the last check exceeds `--ready-max-sync-lag` or is unknown,
+    /// `/readyz` reports 503 `syncing`.
+    #[arg(long, default_value_t = false)]
+    pub ready_requires_synced: bool,
+
+    /// Largest sync lag in blocks still considered synced for readiness.
+    #[arg(long, default_value_t = 0, requires = "ready_requires_synced")]
+    pub ready_max_sync_lag: u64,
+
+    /// Comma-separated bucket upper bounds in seconds for the check latency histograms.
+    ///
+    /// Applies to `check_latency_seconds` and `check_total_duration_seconds`, e.g.
+    /// `0.0005,0.001,0.0025,0.005,0.01` for a local node. Defaults to the
+    /// Prometheus default buckets (5ms to 10s).
+    #[arg(long, value_delimiter = ',', num_args = 1..)]
+    pub latency_buckets: Option<Vec<f64>>,
+
+    /// HTTP server bind address.
+    ///
+    /// The address where the metrics endpoint will be exposed.
+    #[arg(long, default_value = "0.0.0.0")]
+    pub server_addr: String,
+
+    /// HTTP server port.
+    ///
+    /// The port where the metrics endpoint will be exposed.
+    #[arg(long, default_value_t = 3000)]
+    pub server_port: u16,
+
+    /// Number of HTTP server worker threads (at least 1).
+    ///
+    /// The metrics endpoint is nearly idle, so a small number is enough instead
+    /// of actix' default of one worker per CPU.
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u64).range(1..))]
+    pub http_workers: u64,
+
+    /// Retry binding the HTTP server instead of exiting when the port is unavailable.
+    ///
+    /// Checks keep running while the bind is retried with exponential backoff
+    /// (1 second, doubling up to 60 seconds), and every failed attempt is logged.
+    /// Useful when the port is only transiently taken at boot.
+    #[arg(long, default_value_t = false, conflicts_with = "no_http")]
+    pub http_bind_retry: bool,
+
+    /// Path prefix under which all HTTP routes are served.
+    ///
+    /// Useful behind an ingress that forwards e.g. `/wsmon/metrics` without
+    /// stripping the prefix. Empty means routes are served from the root.
+    #[arg(long, default_value = "")]
+    pub base_path: String,
+
+    /// Serve `POST /admin/pause` and `POST /admin/resume` to pause checks at runtime.
+    ///
+    /// While paused, no checks are made and all HTTP routes keep serving the
+    /// last-known values, e.g. during coordinated maintenance.
+    #[arg(long, default_value_t = false, conflicts_with = "no_http")]
+    pub enable_admin: bool,
+
+    /// File with a bearer token required by the admin routes.
+    ///
+    /// Read once at startup. Requests without an `Authorization: Bearer <token>`
+    /// header matching its content are rejected with 401.
+    #[arg(long, requires = "enable_admin")]
+    pub admin_token_file: Option<PathBuf>,
+
+    /// Port of a gRPC server implementing the `grpc.health.v1.Health` service.
+    ///
+    /// Bound on `--server-addr`, for orchestrators probing via the gRPC Health
+    /// Checking Protocol. Reports SERVING while the endpoint is up and NOT_SERVING
+    /// otherwise. Disabled when not set.
+    #[cfg(feature = "grpc-health")]
+    #[arg(long)]
+    pub grpc_health_port: Option<u16>,
+
+    /// Export a numeric field of an RPC response as a gauge, as `NAME=METHOD:PATH`.
+    ///
+    /// After every successful check, METHOD is called without parameters and the
+    /// field at PATH, a simple JSONPath like `$.peers` or `$.queues[0].length`, is
+    /// exported as gauge NAME. Missing or non-numeric values leave the gauge
+    /// unchanged. Can be repeated.
+    ///
+    /// NAME must not clash with a built-in metric, including the `_bucket`, `_sum`
+    /// and `_count` series of the built-in histograms.
+    #[arg(long, value_name = "NAME=METHOD:PATH", value_parser = custom_gauge)]
+    pub custom_gauge: Vec<CustomGauge>,
+
+    /// Region the endpoint is located in, exposed as a label of `endpoint_info`.
+    #[arg(long)]
+    pub region: Option<String>,
+
+    /// Namespace prepended to all metric names, e.g. `acme` for `acme_check_count`.
+    ///
+    /// Must start with a letter or underscore, followed by letters, digits or
+    /// underscores.
+    #[arg(long, value_parser = metric_name_component)]
+    pub metric_namespace: Option<String>,
+
+    /// Subsystem inserted between the namespace and all metric names, e.g.
+    /// `ws` for `acme_ws_check_count`.
+    ///
+    /// Same rules as `--metric-namespace`.
+    #[arg(long, value_parser = metric_name_component)]
+    pub metric_subsystem: Option<String>,
+
+    /// Also expose the last check as blackbox_exporter's `probe_*` metrics.
+    ///
+    /// Adds `probe_success`, `probe_duration_seconds` and
+    /// `probe_dns_lookup_time_seconds`, so dashboards built for blackbox_exporter
+    /// work unmodified. They carry no labels and no metric prefix.
+    #[arg(long, default_value_t = false)]
+    pub blackbox_compat: bool,
+
+    /// HTTP status returned by `/metrics` while the endpoint is down, e.g. 503.
+    ///
+    /// Must be a client or server error status (400 to 599). The metrics body is
+    /// returned either way. By default `/metrics` always returns 200.
+    #[arg(long, value_parser = clap::value_parser!(u16).range(400..600))]
+    pub metrics_unhealthy_status: Option<u16>,
+
+    /// Also expose `check_count_delta`, the checks since the previous scrape.
+    ///
+    /// For push-style collectors expecting deltas instead of monotonic counters.
+    /// Every `/metrics` request resets the delta, so only one collector should scrape.
+    #[arg(long, default_value_t = false)]
+    pub delta_metrics: bool,
+
+    /// Number of most recent failures retained for the `/status` endpoint.
+    #[arg(long, default_value_t = 10)]
+    pub recent_errors: usize,
+
+    /// Length in seconds of the rolling window of `check_error_rate`.
+    #[arg(long, default_value_t = 300, value_parser = clap::value_parser!(u64).range(1..))]
+    pub error_rate_window: u64,
+
+    /// Disable the HTTP server entirely.
+    ///
+    /// Only the monitor runs and no listening socket is opened. Useful in
+    /// locked-down environments where metrics are collected by other means.
+    #[arg(long, default_value_t = false)]
+    pub no_http: bool,
+
+    /// Path of a file the metrics are periodically written to.
+    ///
+    /// Intended for node_exporter's textfile collector, so the path should end
+    /// in `.prom`. The file is replaced atomically on every write.
+    #[arg(long)]
+    pub textfile_path: Option<PathBuf>,
+
+    /// Interval between textfile writes in seconds (at least 1).
+    #[arg(long, default_value_t = 15, value_parser = clap::value_parser!(u64).range(1..))]
+    pub textfile_interval: u64,
+
+    /// Address (`host:port`) of a StatsD/DogStatsD sink the check results are pushed to.
+    ///
+    /// After every check, the outcome, total duration and up/down state are sent
+    /// over UDP with DogStatsD tags. The Prometheus endpoint is unaffected.
+    #[arg(long, value_name = "ADDR")]
+    pub statsd_addr: Option<String>,
+
+    /// Shell command run after each check, for custom integrations.
+    ///
+    /// Runs via `sh -c` with the result in the environment: `WSMON_ENDPOINT`,
+    /// `WSMON_RESULT` (`success` or `failure`), `WSMON_DURATION_SECONDS` and
+    /// `WSMON_ERROR`. Only one command runs at a time; results of checks finishing
+    /// while the previous command is still running are skipped.
+    #[arg(long, value_name = "COMMAND")]
+    #[serde(serialize_with = "redacted")]
+    pub on_result_exec: Option<String>,
+
+    /// Message passed to the `--on-result-exec` command in `WSMON_MESSAGE`.
+    ///
+    /// Placeholders `{endpoint}`, `{state}` (`up` or `down`), `{error}`,
+    /// `{latency}` (seconds) and `{chain}` are replaced by the check's values, so
+    /// scripts can forward the text to a chat or paging system as is. Unknown
+    /// placeholders are rejected at startup.
+    #[arg(long, requires = "on_result_exec")]
+    #[serde(serialize_with = "redacted")]
+    pub alert_template: Option<AlertTemplate>,
+
+    /// Time after which the `--on-result-exec` command is killed, in seconds (at least 1).
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    pub on_result_timeout: u64,
+
+    /// Maximum time to wait for in-flight work on shutdown, in seconds.
+    ///
+    /// On SIGTERM or SIGINT the monitor stops starting new checks and the HTTP
+    /// server stops accepting connections. Anything still running after this
+    /// long is aborted, so the process exits within e.g. Kubernetes' grace period.
+    #[arg(long, default_value_t = 10)]
+    pub shutdown_timeout: u64,
+
+    /// Write a JSON summary of the run to this file on shutdown.
+    ///
+    /// Contains the check totals, failures by reason, the last up/down state and
+    /// the uptime. Written atomically after the in-flight check has finished.
+    #[arg(long)]
+    pub shutdown_report_path: Option<PathBuf>,
+
+    /// Print the effective configuration as JSON and exit.
+    ///
+    /// Credentials embedded in the node and fallback URLs are redacted, and the
+    /// `--on-result-exec` command and `--alert-template` are replaced by
+    /// `REDACTED` as they may contain tokens.
+    #[arg(long, default_value_t = false)]
+    #[serde(skip)]
+    pub print_config: bool,
+
+    /// Validate the configuration and exit without starting the monitor.
+    ///
+    /// Checks URL schemes, the HTTP base path, histogram buckets and that TLS and
+    /// admin token files are readable, then prints every problem found. Exits
+    /// non-zero if there are any. No connections are made.
+    #[arg(long, default_value_t = false, conflicts_with = "print_config")]
+    #[serde(skip)]
+    pub validate: bool,
+}
+
+/// Names of the subcommands, including clap's generated `help`.
+const SUBCOMMANDS: &[&str] = &["monitor", "check", "version", "help"];
+
+/// Flags that are handled without a subcommand.
+const TOP_LEVEL_FLAGS: &[&str] = &["-h", "--help", "-V", "--version"];
+
+impl MonitorArgs {
+    /// Returns the prefix of all metric names composed from the namespace and
+    /// subsystem, e.g. `acme_ws_`, or an empty string if neither is set.
+    pub fn metric_prefix(&self) -> String {
+        [&self.metric_namespace, &self.metric_subsystem]
+            .into_iter()
+            .flatten()
+            .map(|component| format!("{component}_"))
+            .collect()
+    }
+}
+
+/// Validates a namespace or subsystem component of metric names.
+fn metric_name_component(component: &str) -> Result<String> {
+    let mut chars = component.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        bail!("must start with a letter or '_', followed by letters, digits or '_'");
+    }
+
+    Ok(component.to_owned())
+}
+
+/// Parses a custom gauge, rejecting names of built-in metrics.
+fn custom_gauge(spec: &str) -> Result<CustomGauge> {
+    let gauge: CustomGauge = spec.parse()?;
+    if crate::is_builtin_metric(&gauge.name) {
+        bail!("'{}' clashes with a built-in metric", gauge.name);
+    }
+
+    Ok(gauge)
+}
+
+/// Serializes a free-form option as `REDACTED` if it is set, keeping its value out
+/// of `--print-config` output.
+fn redacted<T, S: serde::Serializer>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error> {
+    value.as_ref().map(|_| "REDACTED").serialize(serializer)
+}
+
+/// Normalizes a hex SHA-256 fingerprint, optionally separated by colons, to lowercase.
+fn fingerprint(pin: &str) -> Result<String> {
+    let pin = pin.replace(':', "").to_ascii_lowercase();
+    if pin.len() != 64 || !pin.bytes().all(|b| b.is_ascii_hexdigit()) {
+        bail!("expected a SHA-256 fingerprint as 64 hex digits");
+    }
+
+    Ok(pin)
+}
+
+impl Cli {
+    /// Parses the command line, defaulting to the `monitor` subcommand.
+    ///
+    /// Invocations from before the subcommands were introduced (e.g.
+    /// `ws-endpoint-monitor wss://node --persistent`) are treated as `monitor`.
+    pub fn parse_with_default() -> Self {
+        Self::try_parse_with_default(std::env::args_os().collect()).unwrap_or_else(|e| e.exit())
+    }
+
+    /// Parses the given arguments like [`Cli::parse_with_default`], returning
+    /// errors instead of exiting.
+    fn try_parse_with_default(args: Vec<OsString>) -> Result<Self, clap::Error> {
+        let cli = Self::try_parse_from(with_default_subcommand(args))?;
+        // Only the check mode's value conflicts, so an explicit `rpc` is accepted
+        if let Command::Monitor(args) = &cli.command
+            && args.persistent
+            && args.check.check_mode != CheckMode::Rpc
+        {
+            let mut command = Self::command();
+            command.build();
+            let monitor = command
+                .find_subcommand_mut("monitor")
+                .expect("monitor subcommand");
+            return Err(monitor.error(
+                ErrorKind::ArgumentConflict,
+                format!(
+                    "--persistent requires --check-mode rpc, not '{}'",
+                    args.check.check_mode.as_str()
+                ),
+            ));
+        }
+
+        Ok(cli)
+    }
+}
+
+/// Inserts the `monitor` subcommand unless the arguments already select one.
+///
+/// A subcommand is selected if the first non-flag argument names one, or if only
+/// top-level flags like `--help` are given. Values of global flags given as a
+/// separate argument (e.g. `--log-format compact`) are not taken for one.
+fn with_default_subcommand(mut args: Vec<OsString>) -> Vec<OsString> {
+    let rest = args.get(1..).unwrap_or_default();
+    let value_flags = value_flags();
+    let mut candidates = rest.iter();
+    let mut first = None;
+    while let Some(arg) = candidates.next() {
+        let arg = arg.to_string_lossy();
+        if !arg.starts_with('-') {
+            first = Some(arg);
+            break;
+        }
+        if value_flags.iter().any(|flag| *flag == arg) {
+            candidates.next();
+        }
+    }
+    let explicit = match first {
+        Some(first) => SUBCOMMANDS.iter().any(|name| first == *name),
+        None => rest
+            .iter()
+            .all(|arg| TOP_LEVEL_FLAGS.iter().any(|flag| arg == *flag)),
+    };
+    if !explicit {
+        args.insert(1, "monitor".into());
+    }
+
+    args
+}
+
+/// Returns the long and short forms of the global flags that take a value.
+fn value_flags() -> Vec<String> {
+    Cli::command()
+        .get_arguments()
+        .filter(|arg| arg.get_action().takes_values())
+        .flat_map(|arg| {
+            let long = arg.get_long().map(|long| format!("--{long}"));
+            let short = arg.get_short().map(|short| format!("-{short}"));
+            long.into_iter().chain(short)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cli, Command, LogFormat};
+    use clap::Parser;
+
+    fn parse(args: &[&str]) -> Command {
+        let args = super::with_default_subcommand(args.iter().map(Into::into).collect());
+        Cli::try_parse_from(args).unwrap().command
+    }
+
+    #[test]
+    fn defaults_to_monitor() {
+        let Command::Monitor(args) = parse(&["wsmon", "wss://node", "--persistent"]) else {
+            panic!("expected the monitor subcommand");
+        };
+        assert_eq!(args.check.url.as_deref(), Some("wss://node"));
+        assert!(args.persistent);
+
+        assert!(matches!(
+            parse(&["wsmon", "-v", "wss://node"]),
+            Command::Monitor(_)
+        ));
+    }
+
+    #[test]
+    fn parses_explicit_subcommands() {
+        assert!(matches!(
+            parse(&["wsmon", "check", "wss://node"]),
+            Command::Check(_)
+        ));
+        assert!(matches!(
+            parse(&["wsmon", "-v", "check", "wss://node"]),
+            Command::Check(_)
+        ));
+        assert!(matches!(parse(&["wsmon", "version"]), Command::Version));
+    }
+
+    #[test]
+    fn accepts_log_format_before_subcommands() {
+        for args in [
+            [
+                "wsmon",
+                "--log-format",
+                "compact",
+                "check",
+                "ws://127.0.0.1:1",
+            ]
+            .as_slice(),
+            &["wsmon", "--log-format=compact", "check", "ws://127.0.0.1:1"],
+        ] {
+            let cli = Cli::try_parse_with_default(args.iter().map(Into::into).collect()).unwrap();
+            assert_eq!(cli.log_format, LogFormat::Compact, "{args:?}");
+            assert!(matches!(cli.command, Command::Check(_)), "{args:?}");
+        }
+    }
+
+    #[test]
+    fn skips_global_flag_values_before_subcommands() {
+        for args in [
+            [
+                "wsmon",
+                "--log-format",
+                "compact",
+                "check",
+                "ws://127.0.0.1:1",
+            ]
+            .as_slice(),
+            &["wsmon", "--verbose", "check", "ws://127.0.0.1:1"],
+        ] {
+            assert!(matches!(parse(args), Command::Check(_)), "{args:?}");
+        }
+        assert!(matches!(
+            parse(&["wsmon", "--log-format", "compact", "ws://127.0.0.1:1"]),
+            Command::Monitor(_)
+        ));
+    }
+
+    #[test]
+    fn allows_persistent_only_in_rpc_mode() {
+        let parse =
+            |args: &[&str]| Cli::try_parse_with_default(args.iter().map(Into::into).collect());
+        assert!(parse(&["wsmon", "wss://node", "--persistent"]).is_ok());
+        assert!(parse(&["wsmon", "wss://node", "--check-mode", "rpc", "--persistent"]).is_ok());
+        assert!(parse(&["wsmon", "wss://node", "--check-mode", "tcp"]).is_ok());
+        for mode in ["upgrade", "tcp"] {
+            assert!(parse(&["wsmon", "wss://node", "--check-mode", mode, "--persistent"]).is_err());
+        }
+    }
+
+    #[test]
+    fn requires_exactly_one_url_source() {
+        let parse = |args: &[&str]| {
+            Cli::try_parse_from(super::with_default_subcommand(
+                args.iter().map(Into::into).collect(),
+            ))
+        };
+        assert!(parse(&["wsmon", "check", "--monitor-url-env", "NODE_URL"]).is_ok());
+        assert!(parse(&["wsmon", "check"]).is_err());
+        assert!(
+            parse(&[
+                "wsmon",
+                "check",
+                "wss://node",
+                "--monitor-url-env",
+                "NODE_URL"
+            ])
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn composes_metric_prefix() {
+        let prefix = |args: &[&str]| {
+            let args = [&["wsmon", "wss://node"], args].concat();
+            let Command::Monitor(args) = parse(&args) else {
+                panic!("expected the monitor subcommand");
+            };
+            args.metric_prefix()
+        };
+        assert_eq!(prefix(&[]), "");
+        assert_eq!(prefix(&["--metric-namespace", "acme"]), "acme_");
+        assert_eq!(prefix(&["--metric-subsystem", "ws"]), "ws_");
+        assert_eq!(
+            prefix(&["--metric-namespace", "acme", "--metric-subsystem", "ws"]),
+            "acme_ws_"
+        );
+
+        for component in ["", "1acme", "ac-me", "acme:ws"] {
+            let args = super::with_default_subcommand(
+                ["wsmon", "wss://node", "--metric-namespace", component]
+                    .iter()
+                    .map(Into::into)
+                    .collect(),
+            );
+            assert!(Cli::try_parse_from(args).is_err(), "{component}");
+        }
+    }
+    #[test]
+    fn removes_duplicate_urls() {
+        let Command::Check(mut args) = parse(&[
+            "wsmon",
+            "check",
+            "wss://a",
+            "--fallback-url",
+            "wss://b",
+            "--fallback-url",
+            "wss://a",
+            "--fallback-url",
+            "wss://c",
+            "--fallback-url",
+            "wss://b",
+        ]) else {
+            panic!("expected the check subcommand");
+        };
+        args.resolve_node_url().unwrap();
+        assert_eq!(args.dedup_urls(), ["wss://a", "wss://b"]);
+        assert_eq!(args.fallback_url, ["wss://b", "wss://c"]);
+        assert!(args.dedup_urls().is_empty());
+    }
+
+    #[test]
+    fn normalizes_tls_pins() {
+        let pin = "AB:".repeat(31) + "AB";
+        let Command::Check(args) = parse(&["wsmon", "check", "wss://node", "--tls-pin", &pin])
+        else {
+            panic!("expected the check subcommand");
+        };
+        assert_eq!(args.tls_pins, ["ab".repeat(32)]);
+
+        assert!(super::fingerprint(&"ab".repeat(31)).is_err());
+        assert!(super::fingerprint(&"xy".repeat(32)).is_err());
+    }
+
+    #[test]
+    fn accepts_only_error_statuses_as_unhealthy_metrics_status() {
+        let status = |status: &str| {
+            let args = super::with_default_subcommand(
+                ["wsmon", "wss://node", "--metrics-unhealthy-status", status]
+                    .iter()
+                    .map(Into::into)
+                    .collect(),
+            );
+            match Cli::try_parse_from(args).ok()?.command {
+                Command::Monitor(args) => args.metrics_unhealthy_status,
+                _ => panic!("expected the monitor subcommand"),
+            }
+        };
+        assert_eq!(status("400"), Some(400));
+        assert_eq!(status("503"), Some(503));
+        for rejected in ["100", "200", "302", "399", "600"] {
+            assert_eq!(status(rejected), None, "{rejected}");
+        }
+    }
+
+    #[test]
+    fn redacts_free_form_options_in_config() {
+        let Command::Monitor(args) = parse(&[
+            "wsmon",
+            "wss://node",
+            "--on-result-exec",
+            "curl -H 'Authorization: Bearer secret' https://hooks.example",
+            "--alert-template",
+            "{endpoint} is {state}, key=secret",
+        ]) else {
+            panic!("expected the monitor subcommand");
+        };
+        let config = serde_json::to_value(&args).unwrap();
+        assert_eq!(config["on-result-exec"], "REDACTED");
+        assert_eq!(config["alert-template"], "REDACTED");
+        assert!(!config.to_string().contains("secret"));
+
+        let Command::Monitor(args) = parse(&["wsmon", "wss://node"]) else {
+            panic!("expected the monitor subcommand");
+        };
+        let config = serde_json::to_value(&args).unwrap();
+        assert!(config["on-result-exec"].is_null());
+    }
+
+    #[test]
+    fn rejects_custom_gauges_named_like_builtin_metrics() {
+        for name in [
+            "check_count",
+            "probe_success",
+            "check_total_duration_seconds",
+            "check_total_duration_seconds_count",
+            "ws_upgrade_seconds_bucket",
+            "dns_resolution_seconds_sum",
+        ] {
+            let error = super::custom_gauge(&format!("{name}=system_health:$.peers")).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!("'{name}' clashes with a built-in metric")
+            );
+        }
+
+        // Suffixes only clash for histograms
+        let gauge = super::custom_gauge("check_count_sum=system_health:$.peers").unwrap();
+        assert_eq!(gauge.name, "check_count_sum");
+
+        let args = super::with_default_subcommand(
+            [
+                "wsmon",
+                "wss://node",
+                "--custom-gauge",
+                "peer_count=system_health:$.peers",
+            ]
+            .iter()
+            .map(Into::into)
+            .collect(),
+        );
+        let Err(error) = Cli::try_parse_from(args) else {
+            panic!("expected a clashing custom gauge to be rejected");
+        };
+        assert!(
+            error.to_string().contains("'peer_count' clashes"),
+            "{error}"
+        );
+    }
+}
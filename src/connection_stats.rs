@@ -0,0 +1,88 @@
+//! Lifetime statistics of the connection kept open in persistent mode.
+//!
+//! Ages are measured with tokio's clock, so tests can pause and advance time
+//! instead of sleeping.
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Tracks (re)connects and the age of the current live connection.
+#[derive(Default)]
+pub struct ConnectionStats {
+    /// Number of successfully established connections.
+    established: AtomicUsize,
+    /// Number of connections closed because they exceeded the maximum age.
+    expired: AtomicUsize,
+    /// Number of live connections lost due to a failed check.
+    lost: AtomicUsize,
+    /// When the current live connection was established, if there is one.
+    connected_at: Mutex<Option<Instant>>,
+}
+
+impl ConnectionStats {
+    /// Records a newly established connection, resetting its age.
+    pub fn established(&self) {
+        self.established.fetch_add(1, Ordering::Relaxed);
+        *self.connected_at.lock().unwrap() = Some(Instant::now());
+    }
+
+    /// Records that the current connection was dropped.
+    pub fn dropped(&self) {
+        *self.connected_at.lock().unwrap() = None;
+    }
+
+    /// Records that the current connection was closed for exceeding the maximum age.
+    pub fn expired(&self) {
+        self.expired.fetch_add(1, Ordering::Relaxed);
+        self.dropped();
+    }
+
+    /// Records that the current connection was lost due to a failed check.
+    pub fn lost(&self) {
+        self.lost.fetch_add(1, Ordering::Relaxed);
+        self.dropped();
+    }
+
+    /// Returns the number of successfully established connections.
+    pub fn established_total(&self) -> usize {
+        self.established.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of connections closed for exceeding the maximum age.
+    pub fn expired_total(&self) -> usize {
+        self.expired.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of live connections lost due to a failed check.
+    pub fn lost_total(&self) -> usize {
+        self.lost.load(Ordering::Relaxed)
+    }
+
+    /// Returns the age of the current live connection, if there is one.
+    pub fn age(&self) -> Option<Duration> {
+        self.connected_at.lock().unwrap().map(|at| at.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConnectionStats;
+    use std::time::Duration;
+    use tokio::time;
+
+    #[tokio::test(start_paused = true)]
+    async fn measures_connection_age() {
+        let stats = ConnectionStats::default();
+        assert_eq!(stats.age(), None);
+
+        stats.established();
+        time::advance(Duration::from_secs(90)).await;
+        assert_eq!(stats.age(), Some(Duration::from_secs(90)));
+
+        stats.expired();
+        assert_eq!(stats.age(), None);
+        assert_eq!((stats.established_total(), stats.expired_total()), (1, 1));
+    }
+}
@@ -0,0 +1,153 @@
+//! Gauges exporting a numeric field of an arbitrary RPC response.
+//!
+//! Lets operators graph node-specific values, e.g. a custom runtime metric exposed
+//! via RPC, without changes to the monitor.
+
+use anyhow::{Result, bail};
+use jsonrpsee::core::ClientError;
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::rpc_params;
+use serde::Serialize;
+use std::str::FromStr;
+
+/// A gauge set from a field of an RPC response, parsed from `NAME=METHOD:PATH`.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct CustomGauge {
+    /// Metric name of the gauge.
+    pub name: String,
+    /// RPC method called without parameters.
+    pub method: String,
+    /// Path of the field in the response, e.g. `$.peers` or `$.items[0].value`.
+    pub path: String,
+    /// JSON pointer equivalent to `path`.
+    #[serde(skip)]
+    pointer: String,
+}
+
+impl FromStr for CustomGauge {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let Some((name, rest)) = spec.split_once('=') else {
+            bail!("Expected NAME=METHOD:PATH, got '{spec}'");
+        };
+        let Some((method, path)) = rest.split_once(':') else {
+            bail!("Expected NAME=METHOD:PATH, got '{spec}'");
+        };
+        if !is_metric_name(name) {
+            bail!("Invalid metric name '{name}'");
+        }
+        if method.is_empty() {
+            bail!("Empty RPC method in '{spec}'");
+        }
+
+        Ok(Self {
+            name: name.to_owned(),
+            method: method.to_owned(),
+            pointer: json_pointer(path)?,
+            path: path.to_owned(),
+        })
+    }
+}
+
+impl CustomGauge {
+    /// Calls the RPC method and returns the value of the field.
+    ///
+    /// Returns `None` if the field is missing or not numeric. Numeric strings,
+    /// including `0x`-prefixed hex, are accepted.
+    pub async fn fetch<C: ClientT>(&self, client: &C) -> Result<Option<f64>, ClientError> {
+        let response: serde_json::Value = client.request(&self.method, rpc_params![]).await?;
+        Ok(self.extract(&response))
+    }
+
+    /// Returns the numeric value of the field in `response`, if there is one.
+    fn extract(&self, response: &serde_json::Value) -> Option<f64> {
+        match response.pointer(&self.pointer)? {
+            serde_json::Value::Number(number) => number.as_f64(),
+            serde_json::Value::String(value) => match value.strip_prefix("0x") {
+                Some(digits) => u64::from_str_radix(digits, 16).ok().map(|n| n as f64),
+                None => value.parse().ok(),
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Returns whether `name` is a valid Prometheus metric name.
+fn is_metric_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+/// Converts a simple JSONPath (`$`, `.key` and `[index]` steps) into a JSON pointer.
+fn json_pointer(path: &str) -> Result<String> {
+    let Some(mut rest) = path.strip_prefix('$') else {
+        bail!("Path '{path}' must start with '$'");
+    };
+
+    let mut pointer = String::new();
+    while !rest.is_empty() {
+        let step;
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            (step, rest) = after.split_at(end);
+        } else if let Some(after) = rest.strip_prefix('[') {
+            let Some(end) = after.find(']') else {
+                bail!("Unclosed '[' in path '{path}'");
+            };
+            step = &after[..end];
+            if step.parse::<usize>().is_err() {
+                bail!("Invalid index '{step}' in path '{path}'");
+            }
+            rest = &after[end + 1..];
+        } else {
+            bail!("Unexpected '{rest}' in path '{path}'");
+        }
+        if step.is_empty() {
+            bail!("Empty step in path '{path}'");
+        }
+        pointer.push('/');
+        pointer.push_str(&step.replace('~', "~0").replace('/', "~1"));
+    }
+
+    Ok(pointer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CustomGauge;
+    use serde_json::json;
+
+    #[test]
+    fn parses_gauge_spec() {
+        let gauge: CustomGauge = "queue_length=custom_queueInfo:$.queues[1].length"
+            .parse()
+            .unwrap();
+        assert_eq!(gauge.name, "queue_length");
+        assert_eq!(gauge.method, "custom_queueInfo");
+        assert_eq!(gauge.pointer, "/queues/1/length");
+
+        for spec in [
+            "queue_length",
+            "queue_length=custom_queueInfo",
+            "1queue=custom_queueInfo:$.length",
+            "queue=custom_queueInfo:length",
+            "queue=custom_queueInfo:$.queues[x]",
+        ] {
+            assert!(spec.parse::<CustomGauge>().is_err(), "{spec}");
+        }
+    }
+
+    #[test]
+    fn extracts_numeric_fields_only() {
+        let gauge: CustomGauge = "peers=system_health:$.peers".parse().unwrap();
+        assert_eq!(gauge.extract(&json!({"peers": 42})), Some(42.0));
+        assert_eq!(gauge.extract(&json!({"peers": "0x2a"})), Some(42.0));
+        assert_eq!(gauge.extract(&json!({"peers": "1.5"})), Some(1.5));
+        assert_eq!(gauge.extract(&json!({"peers": true})), None);
+        assert_eq!(gauge.extract(&json!({})), None);
+    }
+}
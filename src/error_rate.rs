@@ -0,0 +1,84 @@
+//! Share of failed checks within a rolling time window.
+//!
+//! Complements the monotonic check counters for collectors that cannot compute
+//! rates, and for simple threshold alerts. Times are measured with tokio's clock,
+//! so tests can pause and advance time instead of sleeping.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Results of the checks within the last `window`, oldest first.
+pub struct ErrorRate {
+    /// Age after which a check no longer counts.
+    window: Duration,
+    /// Completion time and success of each check within the window, oldest first.
+    checks: Mutex<VecDeque<(Instant, bool)>>,
+}
+
+impl ErrorRate {
+    /// Creates an empty rolling window of the given length.
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            checks: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Records the result of a completed check.
+    pub fn record(&self, success: bool) {
+        let now = Instant::now();
+        let mut checks = self.checks.lock().unwrap();
+        self.expire(&mut checks, now);
+        checks.push_back((now, success));
+    }
+
+    /// Returns the share of failed checks within the window, if there were any checks.
+    pub fn rate(&self) -> Option<f64> {
+        let mut checks = self.checks.lock().unwrap();
+        self.expire(&mut checks, Instant::now());
+        if checks.is_empty() {
+            return None;
+        }
+        let failures = checks.iter().filter(|(_, success)| !success).count();
+        Some(failures as f64 / checks.len() as f64)
+    }
+
+    /// Drops the checks that have left the window.
+    fn expire(&self, checks: &mut VecDeque<(Instant, bool)>, now: Instant) {
+        while checks
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > self.window)
+        {
+            checks.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ErrorRate;
+    use std::time::Duration;
+    use tokio::time;
+
+    #[tokio::test(start_paused = true)]
+    async fn rates_failures_within_window() {
+        let rate = ErrorRate::new(Duration::from_secs(60));
+        assert_eq!(rate.rate(), None);
+
+        rate.record(false);
+        time::advance(Duration::from_secs(30)).await;
+        rate.record(true);
+        rate.record(true);
+        rate.record(false);
+        assert_eq!(rate.rate(), Some(0.5));
+
+        // The first failure leaves the window
+        time::advance(Duration::from_secs(31)).await;
+        assert_eq!(rate.rate(), Some(1.0 / 3.0));
+
+        time::advance(Duration::from_secs(60)).await;
+        assert_eq!(rate.rate(), None);
+    }
+}
@@ -0,0 +1,543 @@
+//! Normalization of check failures into a bounded set of reasons.
+//!
+//! Raw error messages are unsuitable as metric labels, so every failure is mapped
+//! to one of the canonical [`ErrorReason`] values before being counted. Failures
+//! are classified from their typed errors where possible, as messages may contain
+//! host names or paths that happen to look like another reason.
+
+use crate::chain::FetchError;
+use crate::transport::{ConnectError, UpgradeRejected, WsError};
+use jsonrpsee::core::ClientError;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio_rustls::rustls;
+use tokio_rustls::rustls::pki_types::InvalidDnsNameError;
+
+/// Canonical reason a check failed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorReason {
+    /// The remote end actively refused the TCP connection.
+    Refused,
+    /// Connecting or waiting for a response took too long.
+    Timeout,
+    /// The host name could not be resolved.
+    Dns,
+    /// The TLS handshake or certificate validation failed.
+    Tls,
+    /// A `wss://` URL was redirected to a plaintext location.
+    TlsDowngrade,
+    /// No certificate presented by the server matched a configured pin.
+    CertPinMismatch,
+    /// The connection was reset or closed unexpectedly.
+    Reset,
+    /// The node answered but was connected to fewer peers than required.
+    LowPeers,
+    /// The gateway throttled the monitor, e.g. with HTTP status 429.
+    RateLimited,
+    /// The node answered but ran a runtime older than the required spec version.
+    OutdatedRuntime,
+    /// The configured credentials could not be loaded, e.g. an empty token file.
+    AuthConfig,
+    /// Any failure not covered by the other reasons.
+    Other,
+}
+
+impl ErrorReason {
+    /// All reasons, in the order they are exposed as metrics.
+    pub const ALL: [ErrorReason; 12] = [
+        ErrorReason::Refused,
+        ErrorReason::Timeout,
+        ErrorReason::Dns,
+        ErrorReason::Tls,
+        ErrorReason::TlsDowngrade,
+        ErrorReason::CertPinMismatch,
+        ErrorReason::Reset,
+        ErrorReason::RateLimited,
+        ErrorReason::LowPeers,
+        ErrorReason::OutdatedRuntime,
+        ErrorReason::AuthConfig,
+        ErrorReason::Other,
+    ];
+
+    /// Label value used for this reason in metrics.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorReason::Refused => "refused",
+            ErrorReason::Timeout => "timeout",
+            ErrorReason::Dns => "dns",
+            ErrorReason::Tls => "tls",
+            ErrorReason::TlsDowngrade => "tls_downgrade",
+            ErrorReason::CertPinMismatch => "cert_pin_mismatch",
+            ErrorReason::Reset => "reset",
+            ErrorReason::RateLimited => "rate_limited",
+            ErrorReason::LowPeers => "low_peers",
+            ErrorReason::OutdatedRuntime => "outdated_runtime",
+            ErrorReason::AuthConfig => "auth_config",
+            ErrorReason::Other => "other",
+        }
+    }
+
+    /// Maps an error to its canonical reason, from the first cause in its chain
+    /// with a known type.
+    ///
+    /// Falls back to [`ErrorReason::from_message`] on the full chain if no cause
+    /// is recognized, e.g. for errors jsonrpsee only reports as text.
+    pub fn from_error(error: &(dyn Error + 'static)) -> Self {
+        Self::from_chain(error).unwrap_or_else(|| Self::from_message(&error_chain(error)))
+    }
+
+    /// Returns the reason of the first typed cause in an error's chain, if any.
+    fn from_chain(error: &(dyn Error + 'static)) -> Option<Self> {
+        let mut source = Some(error);
+        while let Some(e) = source {
+            if let Some(reason) = Self::from_cause(e) {
+                return Some(reason);
+            }
+            source = e.source();
+        }
+        None
+    }
+
+    /// Returns the reason of a single error, if its type determines one.
+    fn from_cause(e: &(dyn Error + 'static)) -> Option<Self> {
+        if let Some(e) = e.downcast_ref::<ConnectError>() {
+            return Some(match e {
+                ConnectError::Dns(_) => ErrorReason::Dns,
+                ConnectError::Timeout(_) => ErrorReason::Timeout,
+                ConnectError::TlsDowngrade(_) => ErrorReason::TlsDowngrade,
+                ConnectError::CertPinMismatch { .. } => ErrorReason::CertPinMismatch,
+                ConnectError::AuthToken { .. } => ErrorReason::AuthConfig,
+            });
+        }
+        if let Some(rejected) = e.downcast_ref::<UpgradeRejected>() {
+            return Some(if rejected.is_rate_limited() {
+                ErrorReason::RateLimited
+            } else {
+                ErrorReason::Other
+            });
+        }
+        if let Some(WsError::Closed(_)) = e.downcast_ref::<WsError>() {
+            return Some(ErrorReason::Reset);
+        }
+        // Wrappers below forward `source()` past the error they wrap, so it is
+        // classified explicitly.
+        if let Some(e) = e.downcast_ref::<FetchError>() {
+            return match e {
+                FetchError::Client(e) => Self::from_client_error(e),
+                FetchError::Deserialization { .. } => Some(ErrorReason::Other),
+            };
+        }
+        if let Some(e) = e.downcast_ref::<ClientError>() {
+            return Self::from_client_error(e);
+        }
+        if e.is::<rustls::Error>() || e.is::<InvalidDnsNameError>() {
+            return Some(ErrorReason::Tls);
+        }
+        if let Some(e) = e.downcast_ref::<io::Error>() {
+            // The TLS stream reports handshake failures as io errors
+            if let Some(inner) = e.get_ref()
+                && let Some(reason) = Self::from_chain(inner)
+            {
+                return Some(reason);
+            }
+            return match e.kind() {
+                io::ErrorKind::ConnectionRefused => Some(ErrorReason::Refused),
+                io::ErrorKind::TimedOut => Some(ErrorReason::Timeout),
+                io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::BrokenPipe
+                | io::ErrorKind::UnexpectedEof => Some(ErrorReason::Reset),
+                _ => None,
+            };
+        }
+        None
+    }
+
+    /// Returns the reason of a jsonrpsee client error, if its variant determines one.
+    ///
+    /// Error responses are left to the message fallback, as gateways report rate
+    /// limiting only in their text.
+    fn from_client_error(e: &ClientError) -> Option<Self> {
+        match e {
+            ClientError::Transport(e) => Self::from_chain(e.as_ref()),
+            ClientError::RestartNeeded(e) => {
+                Self::from_client_error(e).or(Some(ErrorReason::Reset))
+            }
+            ClientError::RequestTimeout => Some(ErrorReason::Timeout),
+            _ => None,
+        }
+    }
+
+    /// Maps an error message to its canonical reason.
+    ///
+    /// The message should contain the full error chain (e.g. `format!("{e:#}")`),
+    /// as the distinguishing detail is often in the underlying io error.
+    pub fn from_message(message: &str) -> Self {
+        let message = message.to_lowercase();
+        let matches = |needles: &[&str]| needles.iter().any(|n| message.contains(n));
+
+        if matches(&["too few peers"]) {
+            ErrorReason::LowPeers
+        } else if matches(&["outdated runtime"]) {
+            ErrorReason::OutdatedRuntime
+        } else if matches(&["auth token file"]) {
+            ErrorReason::AuthConfig
+        } else if matches(&[
+            "status code: 429",
+            "rejected `429`",
+            "too many requests",
+            "rate limit",
+        ]) {
+            ErrorReason::RateLimited
+        } else if matches(&["tls downgrade"]) {
+            ErrorReason::TlsDowngrade
+        } else if matches(&["certificate pin mismatch"]) {
+            ErrorReason::CertPinMismatch
+        } else if matches(&[
+            "failed to lookup address",
+            "name or service not known",
+            "nodename nor servname",
+            "no address associated",
+            "temporary failure in name resolution",
+            "no such host",
+        ]) {
+            ErrorReason::Dns
+        } else if matches(&["connection refused"]) {
+            ErrorReason::Refused
+        } else if matches(&["timeout", "timed out"]) {
+            ErrorReason::Timeout
+        } else if matches(&[
+            "certificate",
+            "tls",
+            "fatal alert",
+            "peer is incompatible",
+            "received corrupt message",
+            "invalid dns name",
+        ]) {
+            ErrorReason::Tls
+        } else if matches(&[
+            "connection reset",
+            "broken pipe",
+            "connection closed",
+            "connection was closed",
+            "restart required",
+            "rpc service disconnected",
+            "unexpected eof",
+        ]) {
+            ErrorReason::Reset
+        } else {
+            ErrorReason::Other
+        }
+    }
+}
+
+/// Failure of a check, classified while its error was still typed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckError {
+    /// Canonical reason of the failure.
+    pub reason: ErrorReason,
+    /// Full error chain, for logs and the recent errors.
+    pub message: String,
+}
+
+impl CheckError {
+    /// Creates a failure the monitor detected itself, e.g. too few peers.
+    pub fn new(reason: ErrorReason, message: String) -> Self {
+        Self { reason, message }
+    }
+
+    /// Classifies an error and formats its chain.
+    pub fn from_error(error: &(dyn Error + 'static)) -> Self {
+        Self {
+            reason: ErrorReason::from_error(error),
+            message: error_chain(error),
+        }
+    }
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Per-reason failure counters.
+#[derive(Default)]
+pub struct ErrorCounters([AtomicUsize; ErrorReason::ALL.len()]);
+
+impl ErrorCounters {
+    /// Increments the counter for the given reason.
+    pub fn inc(&self, reason: ErrorReason) {
+        self.0[reason as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the current count for every reason.
+    pub fn snapshot(&self) -> Vec<(ErrorReason, usize)> {
+        ErrorReason::ALL
+            .iter()
+            .map(|&reason| (reason, self.0[reason as usize].load(Ordering::Relaxed)))
+            .collect()
+    }
+}
+
+/// Formats an error together with its chain of sources, separated by `: `.
+///
+/// Wrapper errors often repeat their source's message in their own, so sources
+/// whose message is already contained in the previous one are skipped.
+pub fn error_chain(error: &(dyn Error + 'static)) -> String {
+    let mut chain = error.to_string();
+    let mut previous = chain.clone();
+    let mut source = error.source();
+    while let Some(e) = source {
+        let message = e.to_string();
+        if !previous.contains(&message) {
+            chain.push_str(": ");
+            chain.push_str(&message);
+        }
+        previous = message;
+        source = e.source();
+    }
+
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorReason, error_chain};
+    use crate::chain::FetchError;
+    use crate::transport::{ConnectError, UpgradeRejected};
+    use anyhow::anyhow;
+    use jsonrpsee::core::ClientError;
+    use std::io;
+    use std::time::Duration;
+    use tokio_rustls::rustls;
+
+    #[test]
+    fn categorizes_refused() {
+        assert_eq!(
+            ErrorReason::from_message("Connection refused (os error 111)"),
+            ErrorReason::Refused
+        );
+    }
+
+    #[test]
+    fn categorizes_timeout() {
+        assert_eq!(
+            ErrorReason::from_message("Connection timeout exceeded: 5s"),
+            ErrorReason::Timeout
+        );
+        assert_eq!(
+            ErrorReason::from_message("Request timeout"),
+            ErrorReason::Timeout
+        );
+        assert_eq!(
+            ErrorReason::from_message("connection timed out (os error 110)"),
+            ErrorReason::Timeout
+        );
+    }
+
+    #[test]
+    fn categorizes_dns() {
+        assert_eq!(
+            ErrorReason::from_message(
+                "failed to lookup address information: Name or service not known"
+            ),
+            ErrorReason::Dns
+        );
+        assert_eq!(
+            ErrorReason::from_message(
+                "failed to lookup address information: Temporary failure in name resolution"
+            ),
+            ErrorReason::Dns
+        );
+    }
+
+    #[test]
+    fn categorizes_tls() {
+        assert_eq!(
+            ErrorReason::from_message("invalid peer certificate: UnknownIssuer"),
+            ErrorReason::Tls
+        );
+        assert_eq!(
+            ErrorReason::from_message("received fatal alert: HandshakeFailure"),
+            ErrorReason::Tls
+        );
+    }
+
+    #[test]
+    fn categorizes_low_peers() {
+        assert_eq!(
+            ErrorReason::from_message("Too few peers: 2 (minimum 5)"),
+            ErrorReason::LowPeers
+        );
+    }
+
+    #[test]
+    fn categorizes_rate_limiting() {
+        for message in [
+            "Connection rejected with status code: 429",
+            "Networking or low-level protocol error: Request rejected `429`",
+            "RPC rate limit exceeded",
+        ] {
+            assert_eq!(
+                ErrorReason::from_message(message),
+                ErrorReason::RateLimited,
+                "{message}"
+            );
+        }
+    }
+
+    #[test]
+    fn categorizes_outdated_runtime() {
+        assert_eq!(
+            ErrorReason::from_message("Outdated runtime: spec version 1002000 (minimum 1003000)"),
+            ErrorReason::OutdatedRuntime
+        );
+    }
+
+    #[test]
+    fn categorizes_auth_config() {
+        assert_eq!(
+            ErrorReason::from_message(
+                "Failed to read auth token file '/run/token': No such file or directory (os error 2)"
+            ),
+            ErrorReason::AuthConfig
+        );
+        assert_eq!(
+            ErrorReason::from_message("Auth token file '/run/tls/token' is empty"),
+            ErrorReason::AuthConfig
+        );
+    }
+
+    #[test]
+    fn categorizes_tls_downgrade() {
+        assert_eq!(
+            ErrorReason::from_message(
+                "TLS downgrade: wss:// URL redirected to 'ws://node.example:9944/'"
+            ),
+            ErrorReason::TlsDowngrade
+        );
+    }
+
+    #[test]
+    fn categorizes_cert_pin_mismatch() {
+        assert_eq!(
+            ErrorReason::from_message(
+                "Certificate pin mismatch: no certificate presented by 'node.example' matches a pinned fingerprint"
+            ),
+            ErrorReason::CertPinMismatch
+        );
+    }
+
+    #[test]
+    fn categorizes_reset() {
+        assert_eq!(
+            ErrorReason::from_message("i/o error: Connection reset by peer (os error 104)"),
+            ErrorReason::Reset
+        );
+        assert_eq!(
+            ErrorReason::from_message(
+                "The background task closed connection closed; restart required"
+            ),
+            ErrorReason::Reset
+        );
+        assert_eq!(
+            ErrorReason::from_message("Broken pipe (os error 32)"),
+            ErrorReason::Reset
+        );
+    }
+
+    #[test]
+    fn categorizes_other() {
+        assert_eq!(
+            ErrorReason::from_message("Method not found"),
+            ErrorReason::Other
+        );
+        assert_eq!(
+            ErrorReason::from_message("Connection rejected with status code: 403"),
+            ErrorReason::Other
+        );
+    }
+
+    #[test]
+    fn categorizes_typed_errors_regardless_of_message() {
+        // Host names and paths in the context must not decide the reason
+        let e = anyhow!(io::Error::from(io::ErrorKind::ConnectionReset))
+            .context("Failed to connect to 'tls-timeout.example/certificate'");
+        assert_eq!(ErrorReason::from_error(e.as_ref()), ErrorReason::Reset);
+
+        let e = anyhow!(ConnectError::AuthToken {
+            path: "/run/tls/token".into(),
+            source: Some(io::Error::from(io::ErrorKind::NotFound)),
+        });
+        assert_eq!(ErrorReason::from_error(e.as_ref()), ErrorReason::AuthConfig);
+
+        let e = anyhow!(ConnectError::TlsDowngrade(
+            "ws://too-many-requests.example/".to_owned()
+        ));
+        assert_eq!(
+            ErrorReason::from_error(e.as_ref()),
+            ErrorReason::TlsDowngrade
+        );
+
+        let e = anyhow!(ConnectError::Timeout(Duration::from_secs(5)));
+        assert_eq!(ErrorReason::from_error(e.as_ref()), ErrorReason::Timeout);
+    }
+
+    #[test]
+    fn categorizes_upgrade_rejections_by_status() {
+        for (status_code, reason) in [(429, ErrorReason::RateLimited), (403, ErrorReason::Other)] {
+            let e = anyhow!(UpgradeRejected {
+                status_code,
+                retry_after: None,
+            });
+            assert_eq!(ErrorReason::from_error(e.as_ref()), reason, "{status_code}");
+        }
+    }
+
+    #[test]
+    fn categorizes_wrapped_tls_errors() {
+        let e = io::Error::new(
+            io::ErrorKind::InvalidData,
+            rustls::Error::InvalidCertificate(rustls::CertificateError::UnknownIssuer),
+        );
+        assert_eq!(ErrorReason::from_error(&e), ErrorReason::Tls);
+    }
+
+    #[test]
+    fn categorizes_client_errors() {
+        let e = FetchError::Client(ClientError::RequestTimeout);
+        assert_eq!(ErrorReason::from_error(&e), ErrorReason::Timeout);
+
+        let e = FetchError::Client(ClientError::Transport(Box::new(io::Error::from(
+            io::ErrorKind::ConnectionReset,
+        ))));
+        assert_eq!(ErrorReason::from_error(&e), ErrorReason::Reset);
+
+        // Error responses only carry text, so their message decides
+        let e = FetchError::Client(ClientError::Custom("RPC rate limit exceeded".into()));
+        assert_eq!(ErrorReason::from_error(&e), ErrorReason::RateLimited);
+    }
+
+    #[test]
+    fn formats_error_chain() {
+        let e = anyhow!(io::Error::from(io::ErrorKind::ConnectionRefused))
+            .context("Failed to connect")
+            .context("Check failed");
+        assert_eq!(
+            error_chain(e.as_ref()),
+            "Check failed: Failed to connect: connection refused"
+        );
+    }
+
+    #[test]
+    fn skips_repeated_source_messages() {
+        // Wrapper whose message already includes its source's message
+        let source = io::Error::from(io::ErrorKind::ConnectionReset);
+        let message = format!("Transport error: {source}");
+        let e = anyhow::Error::from(source).context(message);
+        assert_eq!(error_chain(e.as_ref()), "Transport error: connection reset");
+    }
+}
@@ -0,0 +1,224 @@
+//! Server for the gRPC Health Checking Protocol (`grpc.health.v1.Health`), for
+//! orchestrators probing via gRPC rather than HTTP.
+//!
+//! Only the unary `Check` method is implemented, which is all that probe tools
+//! such as Kubernetes gRPC probes and `grpc_health_probe` use. The messages are
+//! tiny, so they are encoded by hand instead of pulling in a protobuf toolchain.
+
+use crate::AppState;
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use h2::RecvStream;
+use h2::server::{self, SendResponse};
+use http::{HeaderMap, HeaderValue, Request, Response};
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{Level, event};
+
+/// Path of the `Check` method.
+const CHECK_PATH: &str = "/grpc.health.v1.Health/Check";
+
+/// `HealthCheckResponse.ServingStatus` reported while the endpoint is up.
+const SERVING: u8 = 1;
+/// `HealthCheckResponse.ServingStatus` reported while the endpoint is down.
+const NOT_SERVING: u8 = 2;
+
+// gRPC status codes used in responses
+const GRPC_OK: u16 = 0;
+const GRPC_INVALID_ARGUMENT: u16 = 3;
+const GRPC_NOT_FOUND: u16 = 5;
+const GRPC_UNIMPLEMENTED: u16 = 12;
+
+/// gRPC server reporting whether the monitored endpoint is up.
+pub struct GrpcHealthServer {
+    /// Listener accepting gRPC connections.
+    listener: TcpListener,
+}
+
+impl GrpcHealthServer {
+    /// Binds the server, so an unavailable port is reported at startup.
+    pub async fn bind(addr: SocketAddr) -> Result<Self> {
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("Failed to bind gRPC health server to {addr}"))?;
+
+        Ok(Self { listener })
+    }
+
+    /// Serves health checks from the state's up/down status.
+    ///
+    /// This function runs indefinitely. Failed connections are only logged.
+    pub async fn serve(self, state: AppState) {
+        loop {
+            match self.listener.accept().await {
+                Ok((socket, _)) => {
+                    tokio::spawn(serve_connection(socket, state.clone()));
+                }
+                Err(e) => event!(Level::WARN, "Failed to accept gRPC connection: {e}"),
+            }
+        }
+    }
+}
+
+/// Serves the requests of a single HTTP/2 connection.
+async fn serve_connection(socket: TcpStream, state: AppState) {
+    let mut connection = match server::handshake(socket).await {
+        Ok(connection) => connection,
+        Err(e) => {
+            event!(Level::DEBUG, "gRPC handshake failed: {e}");
+            return;
+        }
+    };
+
+    while let Some(request) = connection.accept().await {
+        match request {
+            Ok((request, respond)) => {
+                let up = state.health.is_up();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_request(request, respond, up).await {
+                        event!(Level::DEBUG, "Failed to answer gRPC request: {e}");
+                    }
+                });
+            }
+            Err(e) => {
+                event!(Level::DEBUG, "gRPC connection failed: {e}");
+                return;
+            }
+        }
+    }
+}
+
+/// Answers a single request with the serving status matching `up`.
+async fn handle_request(
+    request: Request<RecvStream>,
+    mut respond: SendResponse<Bytes>,
+    up: bool,
+) -> Result<(), h2::Error> {
+    if request.uri().path() != CHECK_PATH {
+        return respond_status(&mut respond, GRPC_UNIMPLEMENTED);
+    }
+
+    let mut body = request.into_body();
+    let mut message = Vec::new();
+    while let Some(data) = body.data().await {
+        let data = data?;
+        body.flow_control().release_capacity(data.len())?;
+        message.extend_from_slice(&data);
+    }
+
+    // Only the server as a whole is known, named by the empty service
+    match requested_service(&message) {
+        Some("") => {}
+        Some(_) => return respond_status(&mut respond, GRPC_NOT_FOUND),
+        None => return respond_status(&mut respond, GRPC_INVALID_ARGUMENT),
+    }
+
+    let mut stream = respond.send_response(grpc_response(), false)?;
+    stream.send_data(check_response(up), false)?;
+    stream.send_trailers(grpc_status(GRPC_OK))
+}
+
+/// Ends a request with only a gRPC status, as for errors.
+fn respond_status(respond: &mut SendResponse<Bytes>, status: u16) -> Result<(), h2::Error> {
+    let mut response = grpc_response();
+    response.headers_mut().extend(grpc_status(status));
+    respond.send_response(response, true).map(drop)
+}
+
+/// Returns the headers of a gRPC response.
+fn grpc_response() -> Response<()> {
+    let mut response = Response::new(());
+    response.headers_mut().insert(
+        http::header::CONTENT_TYPE,
+        HeaderValue::from_static("application/grpc"),
+    );
+    response
+}
+
+/// Returns the `grpc-status` trailer.
+fn grpc_status(status: u16) -> HeaderMap {
+    let mut trailers = HeaderMap::new();
+    trailers.insert("grpc-status", HeaderValue::from(status));
+    trailers
+}
+
+/// Returns the framed `HealthCheckResponse` message for the given state.
+fn check_response(up: bool) -> Bytes {
+    let status = if up { SERVING } else { NOT_SERVING };
+    // Uncompressed flag, big-endian length, then field 1 (`status`) as varint
+    Bytes::from(vec![0, 0, 0, 0, 2, 0x08, status])
+}
+
+/// Returns the `service` field of a framed `HealthCheckRequest` message.
+///
+/// Returns `None` if the message is malformed or compressed.
+fn requested_service(message: &[u8]) -> Option<&str> {
+    let (header, mut fields) = message.split_first_chunk::<5>()?;
+    let [compressed, len @ ..] = *header;
+    if compressed != 0 || fields.len() != u32::from_be_bytes(len) as usize {
+        return None;
+    }
+
+    let mut service = "";
+    while !fields.is_empty() {
+        let key = read_varint(&mut fields)?;
+        match key & 0x7 {
+            0 => {
+                read_varint(&mut fields)?;
+            }
+            1 => fields = fields.get(8..)?,
+            2 => {
+                let len = usize::try_from(read_varint(&mut fields)?).ok()?;
+                let value = fields.get(..len)?;
+                fields = &fields[len..];
+                if key >> 3 == 1 {
+                    service = std::str::from_utf8(value).ok()?;
+                }
+            }
+            5 => fields = fields.get(4..)?,
+            _ => return None,
+        }
+    }
+
+    Some(service)
+}
+
+/// Reads a protobuf varint from the start of `bytes`, advancing past it.
+fn read_varint(bytes: &mut &[u8]) -> Option<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let (&byte, rest) = bytes.split_first()?;
+        *bytes = rest;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_response, requested_service};
+
+    #[test]
+    fn parses_requested_service() {
+        assert_eq!(requested_service(&[0, 0, 0, 0, 0]), Some(""));
+        assert_eq!(
+            requested_service(&[0, 0, 0, 0, 6, 0x0a, 4, b'n', b'o', b'd', b'e']),
+            Some("node")
+        );
+        // Unknown fields are skipped
+        assert_eq!(requested_service(&[0, 0, 0, 0, 2, 0x10, 1]), Some(""));
+
+        assert_eq!(requested_service(&[0, 0, 0]), None);
+        assert_eq!(requested_service(&[1, 0, 0, 0, 0]), None);
+        assert_eq!(requested_service(&[0, 0, 0, 0, 3, 0x0a, 4, b'n']), None);
+    }
+
+    #[test]
+    fn encodes_serving_status() {
+        assert_eq!(&check_response(true)[..], [0, 0, 0, 0, 2, 0x08, 1]);
+        assert_eq!(&check_response(false)[..], [0, 0, 0, 0, 2, 0x08, 2]);
+    }
+}
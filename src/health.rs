@@ -0,0 +1,102 @@
+//! Endpoint up/down state with hysteresis.
+//!
+//! A single failed check should not mark the endpoint down if the next one succeeds,
+//! so the state only flips after a configurable streak of consecutive results.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Tracks consecutive check results and derives the endpoint up/down state.
+pub struct HealthTracker {
+    /// Consecutive failures required to mark the endpoint down.
+    failure_threshold: u64,
+    /// Consecutive successes required to mark the endpoint up.
+    success_threshold: u64,
+    /// Current streak of consecutive failures.
+    consecutive_failures: AtomicU64,
+    /// Current streak of consecutive successes.
+    consecutive_successes: AtomicU64,
+    /// Whether the endpoint is currently considered up.
+    up: AtomicBool,
+}
+
+impl HealthTracker {
+    /// Creates a tracker for an endpoint that starts out down.
+    pub fn new(failure_threshold: u64, success_threshold: u64) -> Self {
+        Self {
+            failure_threshold,
+            success_threshold,
+            consecutive_failures: AtomicU64::new(0),
+            consecutive_successes: AtomicU64::new(0),
+            up: AtomicBool::new(false),
+        }
+    }
+
+    /// Records the result of a check, flipping the state once a streak reaches its threshold.
+    pub fn record(&self, success: bool) {
+        if success {
+            self.consecutive_failures.store(0, Ordering::Relaxed);
+            let streak = self.consecutive_successes.fetch_add(1, Ordering::Relaxed) + 1;
+            if streak >= self.success_threshold {
+                self.up.store(true, Ordering::Relaxed);
+            }
+        } else {
+            self.consecutive_successes.store(0, Ordering::Relaxed);
+            let streak = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+            if streak >= self.failure_threshold {
+                self.up.store(false, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Returns whether the endpoint is currently considered up.
+    pub fn is_up(&self) -> bool {
+        self.up.load(Ordering::Relaxed)
+    }
+
+    /// Returns the current streak of consecutive failures, 0 after a success.
+    pub fn consecutive_failures(&self) -> u64 {
+        self.consecutive_failures.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HealthTracker;
+
+    #[test]
+    fn requires_failure_streak_to_mark_down() {
+        let health = HealthTracker::new(3, 1);
+        health.record(true);
+        assert!(health.is_up());
+
+        health.record(false);
+        health.record(false);
+        assert!(health.is_up());
+
+        // A success in between resets the failure streak
+        health.record(true);
+        assert_eq!(health.consecutive_failures(), 0);
+        health.record(false);
+        health.record(false);
+        assert!(health.is_up());
+
+        health.record(false);
+        assert!(!health.is_up());
+        assert_eq!(health.consecutive_failures(), 3);
+    }
+
+    #[test]
+    fn requires_success_streak_to_mark_up() {
+        let health = HealthTracker::new(1, 2);
+        assert!(!health.is_up());
+
+        health.record(true);
+        assert!(!health.is_up());
+
+        health.record(true);
+        assert!(health.is_up());
+
+        health.record(false);
+        assert!(!health.is_up());
+    }
+}
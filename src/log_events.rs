@@ -0,0 +1,61 @@
+//! Counting of emitted warning and error log events for self-monitoring.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::Context;
+
+/// Counts of log events at the levels worth alerting on.
+#[derive(Default)]
+pub struct LogEventCounters {
+    /// Number of events logged at WARN level.
+    warn: AtomicUsize,
+    /// Number of events logged at ERROR level.
+    error: AtomicUsize,
+}
+
+impl LogEventCounters {
+    /// Returns the event count per level label, e.g. `("warn", 3)`.
+    pub fn snapshot(&self) -> [(&'static str, usize); 2] {
+        [
+            ("warn", self.warn.load(Ordering::Relaxed)),
+            ("error", self.error.load(Ordering::Relaxed)),
+        ]
+    }
+}
+
+/// Tracing layer incrementing [`LogEventCounters`] for every WARN and ERROR event.
+pub struct CountingLayer(pub Arc<LogEventCounters>);
+
+impl<S: Subscriber> Layer<S> for CountingLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        match *event.metadata().level() {
+            Level::WARN => self.0.warn.fetch_add(1, Ordering::Relaxed),
+            Level::ERROR => self.0.error.fetch_add(1, Ordering::Relaxed),
+            _ => return,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CountingLayer, LogEventCounters};
+    use std::sync::Arc;
+    use tracing::{Level, event};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    #[test]
+    fn counts_warnings_and_errors() {
+        let counters = Arc::new(LogEventCounters::default());
+        let subscriber = tracing_subscriber::registry().with(CountingLayer(counters.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            event!(Level::INFO, "ignored");
+            event!(Level::WARN, "first");
+            event!(Level::WARN, "second");
+            event!(Level::ERROR, "third");
+        });
+
+        assert_eq!(counters.snapshot(), [("warn", 2), ("error", 1)]);
+    }
+}
@@ -4,64 +4,156 @@
 //! by periodically attempting to connect and fetch the finalized block head.
 //! Results are exposed as Prometheus metrics via an HTTP endpoint.
 
-use actix_web::{App, HttpResponse, HttpServer, get, web};
-use anyhow::{Result, bail};
-use clap::Parser;
-use jsonrpsee::client_transport::ws::Url;
-use jsonrpsee::{core::client::ClientT, rpc_params, ws_client::WsClientBuilder};
-use prometheus::{Counter, Encoder, Opts, Registry, TextEncoder};
-use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::time::Duration;
-use tokio::time;
-use tracing::{Level, event};
+use actix_web::http::StatusCode;
+use actix_web::{App, HttpRequest, HttpResponse, HttpServer, get, post, web};
+use anyhow::{Context, Result, bail};
+use block_rate::BlockRate;
+use chain::{ChainStatus, ChainType, FetchError, HeadCache, NodeInfo, StatusCall, is_rpc_error};
+use check_profile::CheckProfile;
+use cli::{CheckArgs, CheckMode, Cli, Command, LogFormat, MonitorArgs};
+use connection_stats::ConnectionStats;
+use custom_gauge::CustomGauge;
+use error_rate::ErrorRate;
+use error_reason::{CheckError, ErrorCounters, ErrorReason, error_chain};
+use health::HealthTracker;
+use jsonrpsee::core::client::ClientT;
+use jsonrpsee::http_client::{HeaderMap, HeaderValue, HttpClient, HttpClientBuilder};
+use jsonrpsee::ws_client::{WsClient, WsClientBuilder};
+use log_events::{CountingLayer, LogEventCounters};
+use node_url::{check_node_url, http_url, redact_url};
+use prometheus::core::{Collector, Desc};
+use prometheus::proto::MetricFamily;
+use prometheus::{
+    Counter, Encoder, Gauge, Histogram, HistogramOpts, HistogramVec, Opts, Registry, TextEncoder,
+};
+use recent_errors::{ErrorRecord, RecentErrors};
+use response_changes::ResponseTracker;
+use result_hook::ResultHook;
+use serde::{Deserialize, Serialize};
+use statsd::StatsdSink;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::{Instrument, Level, event, span};
 use tracing_subscriber::FmtSubscriber;
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use transport::{
+    ClientIdentity, ConnectError, ConnectTimings, TrafficCounters, Transport, TransportOptions,
+    UpgradeRejected,
+};
+use update_lock::UpdateLock;
+use url_stats::{UrlSnapshot, UrlStats};
 
-/// Command line arguments
-#[derive(Clone, Parser)]
-#[command(version, about, long_about = None)]
-struct Args {
-    /// WebSocket URL of the Substrate node to monitor.
-    ///
-    /// This should be a valid WebSocket endpoint (ws:// or wss://).
-    node_url: String,
+mod alert_template;
+mod block_rate;
+mod cert;
+mod chain;
+mod check_profile;
+mod cli;
+mod connection_stats;
+mod custom_gauge;
+mod error_rate;
+mod error_reason;
+#[cfg(feature = "grpc-health")]
+mod grpc_health;
+mod health;
+mod log_events;
+mod node_url;
+mod panics;
+mod recent_errors;
+mod response_changes;
+mod result_hook;
+mod rpc_version;
+mod statsd;
+mod textfile;
+mod transport;
+mod update_lock;
+mod url_stats;
 
-    /// Interval between connection checks in seconds.
-    ///
-    /// The monitor will attempt to connect to the node at this interval.
-    #[arg(long, default_value_t = 60)]
-    monitor_interval: u64,
+/// Histogram buckets for connection phase times, in seconds.
+const PHASE_BUCKETS: &[f64] = &[
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
 
-    /// Timeout for establishing WebSocket connection in seconds.
-    ///
-    /// If the connection cannot be established within this time, it's marked as failed.
-    #[arg(long, default_value_t = 5)]
-    monitor_connection_timeout: u64,
+/// Largest factor the check interval is stretched by while rate limited.
+const MAX_RATE_LIMIT_BACKOFF: u32 = 16;
 
-    /// Timeout for RPC requests in seconds.
-    ///
-    /// After connection is established, this timeout applies to individual RPC calls.
-    #[arg(long, default_value_t = 5)]
-    monitor_request_timeout: u64,
+/// Longest `Retry-After` delay honored, so a bogus header cannot stop checks.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(3600);
 
-    /// HTTP server bind address.
-    ///
-    /// The address where the metrics endpoint will be exposed.
-    #[arg(long, default_value = "0.0.0.0")]
-    server_addr: String,
+/// Delay before the first retry of a failed HTTP server bind.
+const HTTP_BIND_RETRY_DELAY: Duration = Duration::from_secs(1);
 
-    /// HTTP server port.
-    ///
-    /// The port where the metrics endpoint will be exposed.
-    #[arg(long, default_value_t = 3000)]
-    server_port: u16,
+/// Largest delay between retries of a failed HTTP server bind.
+const MAX_HTTP_BIND_RETRY_DELAY: Duration = Duration::from_secs(60);
 
-    /// Enable verbose logging.
-    ///
-    /// When set, changes log level from INFO to DEBUG.
-    #[arg(short, long, default_value_t = false)]
-    verbose: bool,
-}
+/// Names of the built-in metric families other than histograms.
+const BUILTIN_METRICS: &[&str] = &[
+    "blocks_per_second",
+    "bytes_received_total",
+    "bytes_sent_total",
+    "check_count",
+    "check_count_delta",
+    "check_error_rate",
+    "check_error_total",
+    "check_mode_info",
+    "check_schedule_delay_seconds",
+    "configured_connection_timeout_seconds",
+    "configured_request_timeout_seconds",
+    "connection_age_seconds",
+    "connection_established_total",
+    "connection_reconnect_total",
+    "consecutive_failures",
+    "degraded_checks_total",
+    "deserialization_error_total",
+    "endpoint_http_fallback",
+    "endpoint_info",
+    "endpoint_is_archive",
+    "endpoint_state",
+    "endpoint_tls_cert_expiry_timestamp_seconds",
+    "endpoint_up",
+    "finalized_block_age_seconds",
+    "finalized_block_number",
+    "fleet_endpoints_down",
+    "fleet_endpoints_up",
+    "fleet_healthy_ratio",
+    "handler_panics_total",
+    "head_finality_gap_blocks",
+    "keepalive_calls_total",
+    "log_events_total",
+    "monitored_endpoints",
+    "monitored_endpoints_enabled",
+    "monitoring_paused",
+    "peer_count",
+    "probe_dns_lookup_time_seconds",
+    "probe_duration_seconds",
+    "probe_success",
+    "rate_limited_total",
+    "retry_after_honored_total",
+    "rpc_call_count",
+    "runtime_spec_version",
+    "scheduler_pending_checks",
+    "sync_lag_blocks",
+    "time_to_first_success_seconds",
+    "unexpected_change_total",
+    "url_check_count",
+    "url_used",
+];
+
+/// Names of the built-in histograms, exposed as `_bucket`, `_sum` and `_count` series.
+const BUILTIN_HISTOGRAMS: &[&str] = &[
+    "check_latency_seconds",
+    "check_total_duration_seconds",
+    "dns_resolution_seconds",
+    "ws_upgrade_seconds",
+];
 
 /// Shared application state containing metrics counters.
 #[derive(Clone)]
@@ -72,198 +164,3547 @@ struct AppState {
     success: Arc<AtomicUsize>,
     /// Counter for failed connection attempts.
     failure: Arc<AtomicUsize>,
+    /// Counters for failed connection attempts by canonical reason.
+    errors: Arc<ErrorCounters>,
+    /// Most recent failures, for the `/status` endpoint.
+    recent_errors: Arc<RecentErrors>,
+    /// Consecutive result streaks and the derived up/down state.
+    health: Arc<HealthTracker>,
+    /// Whether the connection is kept open between checks.
+    persistent: bool,
+    /// Configured timeout for establishing the WebSocket connection.
+    connection_timeout: Duration,
+    /// Configured timeout for individual RPC requests.
+    request_timeout: Duration,
+    /// Lifetime statistics of the connection kept open in persistent mode.
+    connections: Arc<ConnectionStats>,
+    /// Per-URL results of the primary and fallback URLs.
+    urls: Arc<UrlStats>,
+    /// Block and sync information from the last successful check.
+    chain: Arc<Mutex<Option<ChainStatus>>>,
+    /// TLS certificate expiry (Unix timestamp) seen on the last `wss://` connection.
+    cert_expiry: Arc<Mutex<Option<i64>>>,
+    /// Result of the last check in blackbox_exporter terms, `None` unless enabled.
+    last_probe: Option<Arc<Mutex<Option<Probe>>>>,
+    /// Chain and version discovered on the last new connection.
+    node_info: Arc<Mutex<Option<NodeInfo>>>,
+    /// Whether the node is an archive node, detected once on the first connection.
+    archive: Arc<Mutex<Option<bool>>>,
+    /// Whether the last check fell back to HTTP JSON-RPC, `None` unless allowed.
+    http_fallback: Option<Arc<AtomicBool>>,
+    /// Region the endpoint is located in, exposed in `endpoint_info`.
+    region: Option<String>,
+    /// Prefix of all metric names, from the metric namespace and subsystem.
+    metric_prefix: String,
+    /// How far checks go, exposed in `check_mode_info`.
+    check_mode: CheckMode,
+    /// Delay between the scheduled and actual start of the last monitor check.
+    schedule_delay: Arc<Mutex<Option<Duration>>>,
+    /// Scheduled checks overdue when the last monitor check started.
+    pending_checks: Arc<AtomicUsize>,
+    /// Last values of the chain identity fields, for detecting unexpected changes.
+    responses: Arc<ResponseTracker>,
+    /// Number of the last finalized head, reused while it does not move.
+    finalized: Arc<HeadCache>,
+    /// Number of the last best head, reused while it does not move.
+    best: Arc<HeadCache>,
+    /// Counters for responses that did not match the expected type, by type name.
+    deserialization_errors: Arc<Mutex<BTreeMap<&'static str, usize>>>,
+    /// Counters for status RPC sub-calls, by call and result.
+    rpc_calls: Arc<Mutex<BTreeMap<(&'static str, &'static str), usize>>>,
+    /// Last values of the `--custom-gauge` gauges, by metric name.
+    custom_gauges: Arc<Mutex<BTreeMap<String, f64>>>,
+    /// Histogram of DNS resolution times.
+    dns_resolution: Histogram,
+    /// Histogram of WebSocket upgrade times.
+    ws_upgrade: Histogram,
+    /// Histogram of RPC round-trip times of successful checks, by connection warmth.
+    check_latency: HistogramVec,
+    /// Histogram of total check durations, including connection establishment.
+    check_total_duration: Histogram,
+    /// Groups the updates recording a check, so snapshots see it recorded completely.
+    updates: Arc<UpdateLock>,
+    /// Creation time of the state, i.e. process start.
+    started: Instant,
+    /// Time from `started` to the first successful check, once there was one.
+    first_success: Arc<OnceLock<Duration>>,
+    /// End of the startup grace period for readiness.
+    startup_grace_until: Instant,
+    /// Whether readiness reports ready during the startup grace period.
+    startup_grace_ready: bool,
+    /// Total duration above which a successful check is degraded, disabled if `None`.
+    degraded_latency_threshold: Option<Duration>,
+    /// Whether the last check succeeded, but slower than the degraded threshold.
+    degraded: Arc<AtomicBool>,
+    /// Counter for degraded checks.
+    degraded_checks: Arc<AtomicUsize>,
+    /// Counter for URL checks the gateway throttled.
+    rate_limited: Arc<AtomicUsize>,
+    /// Longest delay the server asked for during the last check, until taken by the monitor.
+    retry_after: Arc<Mutex<Option<Duration>>>,
+    /// Counter for checks delayed as asked by the server.
+    retry_after_honored: Arc<AtomicUsize>,
+    /// Counter for keepalive calls made on the idle persistent connection.
+    keepalives: Arc<AtomicUsize>,
+    /// Largest sync lag still reported as ready, readiness ignores sync if `None`.
+    ready_max_sync_lag: Option<u64>,
+    /// Counters of emitted WARN and ERROR log events.
+    log_events: Arc<LogEventCounters>,
+    /// HTTP status returned by `/metrics` while the endpoint is down, 200 if `None`.
+    metrics_unhealthy_status: Option<StatusCode>,
+    /// Whether `/metrics` also exposes check counts since the previous scrape.
+    delta_metrics: bool,
+    /// Successful and failed check counts at the previous `/metrics` scrape.
+    last_scraped: Arc<Mutex<(usize, usize)>>,
+    /// Payload bytes exchanged with the node over WebSocket.
+    traffic: Arc<TrafficCounters>,
+    /// Whether checks are paused via the admin routes.
+    paused: Arc<AtomicBool>,
+    /// Block production rate estimated from the reported block numbers.
+    block_rate: Arc<BlockRate>,
+    /// Share of failed checks within the rolling window.
+    error_rate: Arc<ErrorRate>,
+    /// Bearer token required by the admin routes, unauthenticated if `None`.
+    admin_token: Option<String>,
+}
+
+/// Point-in-time values of all metrics, read from [`AppState`] on each scrape.
+///
+/// Values recorded once a check is done are read consistently with each other,
+/// see [`update_lock`] for which ones these are.
+struct MetricsSnapshot {
+    /// Successful check count.
+    success: usize,
+    /// Failed check count.
+    failure: usize,
+    /// Successful and failed checks since the previous scrape, only in delta mode.
+    check_delta: Option<(usize, usize)>,
+    /// Failed check count per canonical reason.
+    errors: Vec<(ErrorReason, usize)>,
+    /// Whether the endpoint is currently considered up.
+    up: bool,
+    /// Current streak of consecutive failed checks.
+    consecutive_failures: u64,
+    /// Whether the last check was degraded and the degraded check count, if enabled.
+    degraded: Option<(bool, usize)>,
+    /// Number of URL checks the gateway throttled.
+    rate_limited: usize,
+    /// Number of checks delayed as asked by the server.
+    retry_after_honored: usize,
+    /// Number of keepalive calls on the idle persistent connection.
+    keepalives: usize,
+    /// Whether checks are paused.
+    paused: bool,
+    /// Estimated blocks per second, present after two checks reported a block number.
+    blocks_per_second: Option<f64>,
+    /// Share of failed checks within the rolling window, present if there were any.
+    error_rate: Option<f64>,
+    /// Configured connection timeout.
+    connection_timeout: Duration,
+    /// Configured RPC request timeout.
+    request_timeout: Duration,
+    /// Connection lifetime statistics, present only in persistent mode.
+    connection: Option<ConnectionSnapshot>,
+    /// Per-URL results, present only when fallback URLs are configured.
+    urls: Option<Vec<UrlSnapshot>>,
+    /// Block and sync information, present after the first successful check.
+    chain: Option<ChainStatus>,
+    /// TLS certificate expiry, present after the first `wss://` connection.
+    cert_expiry: Option<i64>,
+    /// Result of the last check, present only in blackbox_exporter compatibility mode.
+    last_probe: Option<Probe>,
+    /// Descriptive node attributes, present once discovered.
+    node_info: Option<NodeInfo>,
+    /// Whether the node is an archive node, present once detected.
+    archive: Option<bool>,
+    /// Whether the last check fell back to HTTP JSON-RPC, present only if allowed.
+    http_fallback: Option<bool>,
+    /// Configured region of the endpoint.
+    region: Option<String>,
+    /// Prefix of all metric names.
+    metric_prefix: String,
+    /// How far checks go.
+    check_mode: CheckMode,
+    /// Scheduling delay of the last check, present after the first monitor check.
+    schedule_delay: Option<Duration>,
+    /// Scheduled checks overdue when the last check started.
+    pending_checks: usize,
+    /// Time from process start to the first successful check, present once there was one.
+    first_success: Option<Duration>,
+    /// Emitted log event count per level.
+    log_events: [(&'static str, usize); 2],
+    /// Number of panics since startup.
+    panics: usize,
+    /// Payload bytes sent to and received from the node.
+    traffic: (u64, u64),
+    /// Unexpected change count per identity field, empty unless change detection is enabled.
+    response_changes: Vec<(&'static str, u64)>,
+    /// Deserialization failure count per target type, for types that failed at least once.
+    deserialization_errors: Vec<(&'static str, usize)>,
+    /// Status RPC sub-call count per call and result.
+    rpc_calls: Vec<((&'static str, &'static str), usize)>,
+    /// Last values of the custom gauges, by metric name.
+    custom_gauges: Vec<(String, f64)>,
+    /// Histogram of DNS resolution times, collected with the counters.
+    dns_resolution: HistogramSnapshot,
+    /// Histogram of WebSocket upgrade times, collected with the counters.
+    ws_upgrade: HistogramSnapshot,
+    /// Histogram of RPC round-trip times, collected with the counters.
+    check_latency: HistogramSnapshot,
+    /// Histogram of total check durations, collected with the counters.
+    check_total_duration: HistogramSnapshot,
+}
+
+/// Values of a histogram collected at snapshot time, registered in place of it.
+#[derive(Clone)]
+struct HistogramSnapshot {
+    /// Descriptors of the histogram.
+    descs: Vec<Desc>,
+    /// Collected values of the histogram.
+    families: Vec<MetricFamily>,
+}
+
+impl HistogramSnapshot {
+    /// Collects the current values of `histogram`.
+    fn new(histogram: &impl Collector) -> Self {
+        Self {
+            descs: histogram.desc().into_iter().cloned().collect(),
+            families: histogram.collect(),
+        }
+    }
+}
+
+impl Collector for HistogramSnapshot {
+    fn desc(&self) -> Vec<&Desc> {
+        self.descs.iter().collect()
+    }
+
+    fn collect(&self) -> Vec<MetricFamily> {
+        self.families.clone()
+    }
+}
+
+/// Point-in-time connection lifetime statistics.
+struct ConnectionSnapshot {
+    /// Number of successfully established connections.
+    established_total: usize,
+    /// Number of proactive reconnects after exceeding the maximum age.
+    expired_total: usize,
+    /// Number of reconnects after a live connection failed.
+    lost_total: usize,
+    /// Age of the current live connection, if there is one.
+    age: Option<Duration>,
+}
+
+impl AppState {
+    /// Creates the state for checking the node described by `args`.
+    ///
+    /// # Arguments
+    ///
+    /// * `args` - Node URLs and timeouts
+    /// * `health` - Tracker deriving the up/down state from check results
+    /// * `persistent` - Whether the connection is kept open between checks
+    /// * `startup_grace` - Time after startup during which readiness reports "starting"
+    /// * `startup_grace_ready` - Whether "starting" is reported as ready
+    /// * `log_events` - Counters of emitted log events, shared with the tracing layer
+    /// * `recent_errors` - Number of most recent failures retained for `/status`
+    fn new(
+        args: &CheckArgs,
+        health: HealthTracker,
+        persistent: bool,
+        startup_grace: Duration,
+        startup_grace_ready: bool,
+        log_events: Arc<LogEventCounters>,
+        recent_errors: usize,
+    ) -> Result<Self> {
+        // Credentials must not leak into metric labels
+        let endpoint = redact_url(&args.node_url);
+        Ok(Self {
+            ws_endpoint: endpoint.clone(),
+            success: Arc::new(AtomicUsize::new(0)),
+            failure: Arc::new(AtomicUsize::new(0)),
+            errors: Arc::new(ErrorCounters::default()),
+            recent_errors: Arc::new(RecentErrors::new(recent_errors)),
+            health: Arc::new(health),
+            persistent,
+            connection_timeout: Duration::from_secs(args.monitor_connection_timeout),
+            request_timeout: Duration::from_secs(args.monitor_request_timeout),
+            connections: Arc::new(ConnectionStats::default()),
+            urls: Arc::new(UrlStats::new(
+                node_urls(args).iter().map(|url| redact_url(url)).collect(),
+            )),
+            chain: Arc::new(Mutex::new(None)),
+            cert_expiry: Arc::new(Mutex::new(None)),
+            last_probe: None,
+            node_info: Arc::new(Mutex::new(None)),
+            archive: Arc::new(Mutex::new(None)),
+            http_fallback: args
+                .allow_http_fallback
+                .then(|| Arc::new(AtomicBool::new(false))),
+            region: None,
+            check_mode: args.check_mode,
+            metric_prefix: String::new(),
+            schedule_delay: Arc::new(Mutex::new(None)),
+            pending_checks: Arc::new(AtomicUsize::new(0)),
+            responses: Arc::new(ResponseTracker::default()),
+            finalized: Arc::new(HeadCache::default()),
+            best: Arc::new(HeadCache::default()),
+            deserialization_errors: Arc::new(Mutex::new(BTreeMap::new())),
+            rpc_calls: Arc::new(Mutex::new(BTreeMap::new())),
+            custom_gauges: Arc::new(Mutex::new(BTreeMap::new())),
+            dns_resolution: endpoint_histogram(
+                "dns_resolution_seconds",
+                "Histogram of DNS resolution times of the endpoint host",
+                &endpoint,
+                PHASE_BUCKETS,
+            )?,
+            ws_upgrade: endpoint_histogram(
+                "ws_upgrade_seconds",
+                "Histogram of WebSocket upgrade (HTTP 101 exchange) times",
+                &endpoint,
+                PHASE_BUCKETS,
+            )?,
+            check_latency: check_latency_histogram(&endpoint, prometheus::DEFAULT_BUCKETS)?,
+            check_total_duration: check_total_duration_histogram(
+                &endpoint,
+                prometheus::DEFAULT_BUCKETS,
+            )?,
+            updates: Arc::new(UpdateLock::default()),
+            started: Instant::now(),
+            first_success: Arc::new(OnceLock::new()),
+            startup_grace_until: Instant::now() + startup_grace,
+            startup_grace_ready,
+            degraded_latency_threshold: None,
+            degraded: Arc::new(AtomicBool::new(false)),
+            degraded_checks: Arc::new(AtomicUsize::new(0)),
+            rate_limited: Arc::new(AtomicUsize::new(0)),
+            retry_after: Arc::new(Mutex::new(None)),
+            retry_after_honored: Arc::new(AtomicUsize::new(0)),
+            keepalives: Arc::new(AtomicUsize::new(0)),
+            ready_max_sync_lag: None,
+            log_events,
+            metrics_unhealthy_status: None,
+            delta_metrics: false,
+            last_scraped: Arc::new(Mutex::new((0, 0))),
+            traffic: Arc::default(),
+            paused: Arc::new(AtomicBool::new(false)),
+            block_rate: Arc::default(),
+            error_rate: Arc::new(ErrorRate::new(Duration::ZERO)),
+            admin_token: None,
+        })
+    }
+
+    /// Replaces the buckets of the check latency and total duration histograms.
+    ///
+    /// Fails if the buckets are not in increasing order.
+    fn with_latency_buckets(mut self, buckets: &[f64]) -> Result<Self> {
+        self.check_latency = check_latency_histogram(&self.ws_endpoint, buckets)
+            .context("Invalid --latency-buckets")?;
+        self.check_total_duration = check_total_duration_histogram(&self.ws_endpoint, buckets)
+            .context("Invalid --latency-buckets")?;
+        Ok(self)
+    }
+
+    /// Reads the current values of all metrics.
+    ///
+    /// Checks being recorded concurrently are either included completely or not at all.
+    fn snapshot(&self) -> MetricsSnapshot {
+        self.updates.read(|| MetricsSnapshot {
+            success: self.success.load(Ordering::Relaxed),
+            failure: self.failure.load(Ordering::Relaxed),
+            check_delta: None,
+            errors: self.errors.snapshot(),
+            up: self.health.is_up(),
+            consecutive_failures: self.health.consecutive_failures(),
+            degraded: self.degraded_latency_threshold.map(|_| {
+                (
+                    self.degraded.load(Ordering::Relaxed),
+                    self.degraded_checks.load(Ordering::Relaxed),
+                )
+            }),
+            rate_limited: self.rate_limited.load(Ordering::Relaxed),
+            retry_after_honored: self.retry_after_honored.load(Ordering::Relaxed),
+            keepalives: self.keepalives.load(Ordering::Relaxed),
+            paused: self.paused.load(Ordering::Relaxed),
+            blocks_per_second: self.block_rate.blocks_per_second(),
+            error_rate: self.error_rate.rate(),
+            connection_timeout: self.connection_timeout,
+            request_timeout: self.request_timeout,
+            connection: self.persistent.then(|| ConnectionSnapshot {
+                established_total: self.connections.established_total(),
+                expired_total: self.connections.expired_total(),
+                lost_total: self.connections.lost_total(),
+                age: self.connections.age(),
+            }),
+            urls: self.urls.has_fallbacks().then(|| self.urls.snapshot()),
+            chain: self.chain.lock().unwrap().clone(),
+            cert_expiry: *self.cert_expiry.lock().unwrap(),
+            last_probe: self
+                .last_probe
+                .as_ref()
+                .and_then(|probe| *probe.lock().unwrap()),
+            node_info: self.node_info.lock().unwrap().clone(),
+            archive: *self.archive.lock().unwrap(),
+            http_fallback: self
+                .http_fallback
+                .as_ref()
+                .map(|fallback| fallback.load(Ordering::Relaxed)),
+            region: self.region.clone(),
+            check_mode: self.check_mode,
+            metric_prefix: self.metric_prefix.clone(),
+            schedule_delay: *self.schedule_delay.lock().unwrap(),
+            pending_checks: self.pending_checks.load(Ordering::Relaxed),
+            first_success: self.first_success.get().copied(),
+            log_events: self.log_events.snapshot(),
+            panics: panics::count(),
+            traffic: (self.traffic.sent(), self.traffic.received()),
+            response_changes: self.responses.change_counts(),
+            deserialization_errors: self
+                .deserialization_errors
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(target, count)| (*target, *count))
+                .collect(),
+            rpc_calls: self
+                .rpc_calls
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(key, count)| (*key, *count))
+                .collect(),
+            custom_gauges: self
+                .custom_gauges
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(name, value)| (name.clone(), *value))
+                .collect(),
+            dns_resolution: HistogramSnapshot::new(&self.dns_resolution),
+            ws_upgrade: HistogramSnapshot::new(&self.ws_upgrade),
+            check_latency: HistogramSnapshot::new(&self.check_latency),
+            check_total_duration: HistogramSnapshot::new(&self.check_total_duration),
+        })
+    }
+
+    /// Returns the successful and failed checks since the previous call.
+    ///
+    /// Remembers the snapshot's counts, so every `/metrics` scrape resets the delta.
+    fn scrape_delta(&self, snapshot: &MetricsSnapshot) -> (usize, usize) {
+        let mut last = self.last_scraped.lock().unwrap();
+        let delta = (
+            snapshot.success.saturating_sub(last.0),
+            snapshot.failure.saturating_sub(last.1),
+        );
+        *last = (snapshot.success, snapshot.failure);
+        delta
+    }
+
+    /// Returns the readiness status code and message for `/readyz`.
+    ///
+    /// Readiness follows the endpoint up/down state, except during the startup
+    /// grace period before the first check has completed. If required, an up
+    /// endpoint is only ready once its sync lag is small enough.
+    fn readiness(&self) -> (StatusCode, &'static str) {
+        let checked = self.success.load(Ordering::Relaxed) + self.failure.load(Ordering::Relaxed);
+        if checked == 0 && Instant::now() < self.startup_grace_until {
+            let status = if self.startup_grace_ready {
+                StatusCode::OK
+            } else {
+                StatusCode::SERVICE_UNAVAILABLE
+            };
+            (status, "starting")
+        } else if !self.health.is_up() {
+            (StatusCode::SERVICE_UNAVAILABLE, "not ready")
+        } else if let Some(max_lag) = self.ready_max_sync_lag
+            && self
+                .chain
+                .lock()
+                .unwrap()
+                .as_ref()
+                .and_then(|status| status.sync_lag_blocks)
+                .is_none_or(|lag| lag > max_lag)
+        {
+            (StatusCode::SERVICE_UNAVAILABLE, "syncing")
+        } else {
+            (StatusCode::OK, "ready")
+        }
+    }
+
+    /// Records a complete check as a single update.
+    ///
+    /// # Arguments
+    ///
+    /// * `attempts` - Index, result and measurements of every URL tried
+    /// * `result` - Overall result, with the error chain of the last URL on failure
+    /// * `duration` - Total duration of the check
+    fn record_check(
+        &self,
+        attempts: Vec<(usize, bool, UrlCheck)>,
+        result: &Result<(), CheckError>,
+        duration: Duration,
+    ) {
+        self.updates.update(|| {
+            let dns_lookup = attempts
+                .last()
+                .and_then(|(_, _, measured)| measured.timings.dns);
+            if let Some(fallback) = &self.http_fallback {
+                let active = result.is_ok()
+                    && attempts
+                        .last()
+                        .is_some_and(|(_, _, measured)| measured.http_fallback);
+                if fallback.swap(active, Ordering::Relaxed) != active {
+                    if active {
+                        event!(
+                            Level::WARN,
+                            "WebSocket upgrade rejected, checking over HTTP"
+                        );
+                    } else {
+                        event!(Level::INFO, "Checking over WebSocket again");
+                    }
+                }
+            }
+            for (index, ok, measured) in attempts {
+                self.urls.record(index, ok);
+                // DNS resolution is skipped for IP literals, so there is nothing to observe
+                if let Some(dns) = measured.timings.dns {
+                    self.dns_resolution.observe(dns.as_secs_f64());
+                }
+                if let Some(upgrade) = measured.timings.upgrade {
+                    self.ws_upgrade.observe(upgrade.as_secs_f64());
+                }
+                if let Some(expiry) = measured.cert_expiry {
+                    *self.cert_expiry.lock().unwrap() = Some(expiry);
+                }
+                self.record_rpc_calls(&measured.calls);
+                if measured.rate_limited {
+                    self.rate_limited.fetch_add(1, Ordering::Relaxed);
+                }
+                if let Some(delay) = measured.retry_after {
+                    let mut retry_after = self.retry_after.lock().unwrap();
+                    *retry_after = Some(retry_after.map_or(delay, |longest| longest.max(delay)));
+                }
+                if let Some(latency) = measured.latency {
+                    self.check_latency
+                        .with_label_values(&[if measured.warm { "true" } else { "false" }])
+                        .observe(latency);
+                }
+                if let Some(status) = measured.status {
+                    if let Some(number) = status.finalized_block_number {
+                        self.block_rate.observe(number);
+                    }
+                    *self.chain.lock().unwrap() = Some(status);
+                }
+            }
+            let degraded = result.is_ok()
+                && self
+                    .degraded_latency_threshold
+                    .is_some_and(|threshold| duration > threshold);
+            self.degraded.store(degraded, Ordering::Relaxed);
+            if degraded {
+                self.degraded_checks.fetch_add(1, Ordering::Relaxed);
+            }
+            match result {
+                Ok(()) => {
+                    self.success.fetch_add(1, Ordering::Relaxed);
+                    self.error_rate.record(true);
+                    self.health.record(true);
+                    self.first_success.get_or_init(|| self.started.elapsed());
+                }
+                Err(error) => {
+                    self.record_failure(error);
+                    self.error_rate.record(false);
+                }
+            }
+            self.check_total_duration.observe(duration.as_secs_f64());
+            if let Some(probe) = &self.last_probe {
+                *probe.lock().unwrap() = Some(Probe {
+                    success: result.is_ok(),
+                    duration,
+                    dns_lookup: dns_lookup.unwrap_or_default(),
+                });
+            }
+        });
+    }
+
+    /// Records the results of the status RPC sub-calls made during a check.
+    fn record_rpc_calls(&self, calls: &[(StatusCall, bool)]) {
+        let mut counters = self.rpc_calls.lock().unwrap();
+        for (call, ok) in calls {
+            let result = if *ok { "success" } else { "failure" };
+            *counters.entry((call.as_str(), result)).or_default() += 1;
+        }
+    }
+
+    /// Records a failed check under its reason.
+    fn record_failure(&self, error: &CheckError) {
+        self.failure.fetch_add(1, Ordering::Relaxed);
+        self.health.record(false);
+        self.errors.inc(error.reason);
+        self.recent_errors.record(error.reason, &error.message);
+    }
+
+    /// Counts and logs a response that did not match the expected type.
+    ///
+    /// Other errors are ignored, so this can be called with any fetch result.
+    fn record_deserialization_error(&self, url: &str, error: &FetchError) {
+        if let FetchError::Deserialization {
+            method,
+            target,
+            raw,
+            ..
+        } = error
+        {
+            event!(
+                Level::DEBUG,
+                url,
+                method,
+                target,
+                %raw,
+                "Response did not match the expected type"
+            );
+            *self
+                .deserialization_errors
+                .lock()
+                .unwrap()
+                .entry(target)
+                .or_default() += 1;
+        }
+    }
+}
+
+/// Settings of a check, derived from [`CheckArgs`].
+#[derive(Clone)]
+struct MonitorConfig {
+    /// WebSocket URLs of the node to monitor, primary first.
+    urls: Vec<String>,
+    /// Timeout for establishing the WebSocket connection.
+    connection_timeout: Duration,
+    /// Timeout for individual RPC requests.
+    request_timeout: Duration,
+    /// RPC preset used for checks.
+    chain_type: ChainType,
+    /// How far checks go before they count as successful.
+    check_mode: CheckMode,
+    /// Count JSON-RPC error responses as successful checks.
+    ignore_rpc_errors: bool,
+    /// Minimum number of peers for a check to succeed.
+    min_peers: Option<u64>,
+    /// Minimum runtime spec version for a check to succeed.
+    min_spec_version: Option<u64>,
+    /// Query the block and state at the reported head as part of the check.
+    pinned_head_check: bool,
+    /// Number of status RPC sub-calls that must succeed, all of them if `None`.
+    rpc_quorum: Option<usize>,
+    /// Keep the connection open between checks instead of reconnecting.
+    persistent: bool,
+    /// Compare chain identity fields with the previous check.
+    detect_response_changes: bool,
+    /// Skip the finalized block number lookup while the finalized head does not move.
+    cache_finalized_number: bool,
+    /// Fetch the best head to expose its distance to the finalized head.
+    finality_gap: bool,
+    /// Fetch the finalized block's timestamp to expose its age.
+    block_age: bool,
+    /// Fetch the runtime spec version, also implied by a minimum spec version.
+    runtime_version: bool,
+    /// Age after which the persistent connection is proactively re-established.
+    connection_max_age: Option<Duration>,
+    /// Idle time after which a keepalive call is made on the persistent connection.
+    idle_keepalive: Option<Duration>,
+    /// Discover the chain and node version on every new connection.
+    discover_node_info: bool,
+    /// Gauges set from RPC response fields after every successful check.
+    custom_gauges: Vec<CustomGauge>,
+    /// Retry over HTTP JSON-RPC when the WebSocket upgrade is rejected.
+    allow_http_fallback: bool,
+    /// Check intervals overriding the default one during their time windows.
+    check_profiles: Vec<CheckProfile>,
+    /// Delay the next check while the gateway throttles the monitor.
+    rate_limit_backoff: bool,
+    /// Delay the next check as long as a rejected upgrade's `Retry-After` asks.
+    honor_retry_after: bool,
+}
+
+impl MonitorConfig {
+    /// Creates the settings for a one-shot check of the node described by `args`.
+    fn new(args: &CheckArgs) -> Self {
+        Self {
+            urls: node_urls(args),
+            connection_timeout: Duration::from_secs(args.monitor_connection_timeout),
+            request_timeout: Duration::from_secs(args.monitor_request_timeout),
+            chain_type: args.chain_type,
+            check_mode: args.check_mode,
+            ignore_rpc_errors: args.ignore_rpc_errors,
+            min_peers: args.min_peers,
+            min_spec_version: args.min_spec_version,
+            pinned_head_check: args.pinned_head_check,
+            rpc_quorum: args.rpc_quorum.map(|quorum| quorum as usize),
+            persistent: false,
+            detect_response_changes: false,
+            cache_finalized_number: false,
+            finality_gap: false,
+            block_age: false,
+            runtime_version: args.min_spec_version.is_some(),
+            connection_max_age: None,
+            idle_keepalive: None,
+            discover_node_info: false,
+            custom_gauges: Vec::new(),
+            allow_http_fallback: args.allow_http_fallback,
+            check_profiles: Vec::new(),
+            rate_limit_backoff: false,
+            honor_retry_after: false,
+        }
+    }
+
+    /// Creates the settings for continuously monitoring the node described by `args`.
+    fn monitor(args: &MonitorArgs) -> Self {
+        Self {
+            persistent: args.persistent,
+            detect_response_changes: args.detect_response_changes,
+            cache_finalized_number: args.cache_finalized_number,
+            finality_gap: args.finality_gap,
+            block_age: args.block_age,
+            runtime_version: args.runtime_version || args.check.min_spec_version.is_some(),
+            connection_max_age: (args.connection_max_age > 0)
+                .then(|| Duration::from_secs(args.connection_max_age)),
+            idle_keepalive: args.idle_keepalive.map(Duration::from_secs),
+            discover_node_info: true,
+            custom_gauges: args.custom_gauge.clone(),
+            check_profiles: args.check_profile.clone(),
+            rate_limit_backoff: args.rate_limit_backoff,
+            honor_retry_after: args.honor_retry_after,
+            ..Self::new(&args.check)
+        }
+    }
 }
 
-/// Initializes logging, spawns the connection monitor task, and starts the HTTP server
-/// for metrics exposure.
+/// Initializes logging and runs the selected subcommand.
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
     // Parse command line arguments
-    let args = Args::parse();
+    let cli = Cli::parse_with_default();
 
-    // Initialize tracing subscriber with appropriate log level
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(if args.verbose {
+    // Initialize tracing subscriber with appropriate log level and format. In
+    // one-shot mode stdout is reserved for the check result, so logs go to stderr.
+    let writer = match cli.command {
+        Command::Check(_) => BoxMakeWriter::new(std::io::stderr),
+        _ => BoxMakeWriter::new(std::io::stdout),
+    };
+    let builder = FmtSubscriber::builder()
+        .with_max_level(if cli.verbose {
             Level::DEBUG
         } else {
             Level::INFO
         })
-        .finish();
-    tracing::subscriber::set_global_default(subscriber)
-        .expect("Failed to set default tracing subscriber");
+        .with_writer(writer);
+    let log_events = Arc::new(LogEventCounters::default());
+    let counting_layer = CountingLayer(log_events.clone());
+    let result = match cli.log_format {
+        LogFormat::Full => {
+            tracing::subscriber::set_global_default(builder.finish().with(counting_layer))
+        }
+        LogFormat::Compact => {
+            tracing::subscriber::set_global_default(builder.compact().finish().with(counting_layer))
+        }
+    };
+    result.expect("Failed to set default tracing subscriber");
+    panics::install_hook();
 
-    // Validate provided node URL
-    check_node_url(&args.node_url)?;
+    match cli.command {
+        Command::Monitor(args) if args.validate => Ok(validate_config(*args)),
+        Command::Monitor(args) => {
+            run_monitor(*args, log_events).await?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Check(args) => run_check(*args).await,
+        Command::Version => {
+            println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"));
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+/// Spawns the connection monitor task and starts the HTTP server for metrics exposure.
+///
+/// Runs until a shutdown signal is received.
+///
+/// # Arguments
+///
+/// * `args` - Monitor arguments
+/// * `log_events` - Counters of emitted log events, exposed as metrics
+async fn run_monitor(mut args: MonitorArgs, log_events: Arc<LogEventCounters>) -> Result<()> {
+    resolve_node_urls(&mut args.check)?;
+
+    // Print the effective configuration without starting anything
+    if args.print_config {
+        let mut config = args.clone();
+        config.check.node_url = redact_url(&config.check.node_url);
+        for url in &mut config.check.fallback_url {
+            *url = redact_url(url);
+        }
+        println!("{}", serde_json::to_string_pretty(&config)?);
+        return Ok(());
+    }
+
+    // Validate provided node URLs
+    check_node_urls(&args.check)?;
+
+    // Validate and normalize the HTTP base path
+    let base_path = normalize_base_path(&args.base_path)?;
 
-    // Initialize shared atomic counters
-    let success_counter = Arc::new(AtomicUsize::new(0));
-    let failure_counter = Arc::new(AtomicUsize::new(0));
+    // Build the WebSocket transport used for connection checks
+    let transport = build_transport(&args.check)?;
 
-    // Create application state
+    // Create application state with shared atomic counters
     let app_state = AppState {
-        ws_endpoint: args.node_url.clone(),
-        success: Arc::clone(&success_counter),
-        failure: Arc::clone(&failure_counter),
+        metrics_unhealthy_status: args
+            .metrics_unhealthy_status
+            .map(StatusCode::from_u16)
+            .transpose()?,
+        region: args.region.clone(),
+        metric_prefix: args.metric_prefix(),
+        last_probe: args.blackbox_compat.then(Arc::default),
+        delta_metrics: args.delta_metrics,
+        degraded_latency_threshold: args
+            .degraded_latency_threshold
+            .map(Duration::try_from_secs_f64)
+            .transpose()
+            .context("Invalid --degraded-latency-threshold")?,
+        ready_max_sync_lag: args
+            .ready_requires_synced
+            .then_some(args.ready_max_sync_lag),
+        traffic: transport.traffic(),
+        error_rate: Arc::new(ErrorRate::new(Duration::from_secs(args.error_rate_window))),
+        admin_token: args
+            .admin_token_file
+            .as_deref()
+            .map(read_admin_token)
+            .transpose()?,
+        ..AppState::new(
+            &args.check,
+            HealthTracker::new(args.failure_threshold, args.success_threshold),
+            args.persistent,
+            Duration::from_secs(args.startup_grace),
+            args.startup_grace_ready,
+            log_events,
+            args.recent_errors,
+        )?
     };
+    let app_state = match &args.latency_buckets {
+        Some(buckets) => app_state.with_latency_buckets(buckets)?,
+        None => app_state,
+    };
+
+    // Connect the StatsD sink, if enabled
+    let statsd = match &args.statsd_addr {
+        Some(addr) => Some(StatsdSink::connect(addr, &app_state.ws_endpoint).await?),
+        None => None,
+    };
+
+    // Cancelled once a shutdown signal is received
+    let shutdown = CancellationToken::new();
+    tokio::spawn(wait_for_shutdown_signal(shutdown.clone()));
 
     // Spawn connection monitor task
-    let _connection_monitor = tokio::spawn(connection_monitor(
-        args.node_url,
-        args.monitor_interval,
-        args.monitor_connection_timeout,
-        args.monitor_request_timeout,
-        success_counter,
-        failure_counter,
+    let connection_monitor = tokio::spawn(connection_monitor(
+        MonitorConfig::monitor(&args),
+        Duration::from_secs(args.monitor_interval),
+        transport,
+        app_state.clone(),
+        args.on_result_exec.clone().map(|command| {
+            ResultHook::new(
+                command,
+                Duration::from_secs(args.on_result_timeout),
+                args.alert_template.clone(),
+            )
+        }),
+        statsd,
+        shutdown.clone(),
     ));
+    let shutdown_timeout = Duration::from_secs(args.shutdown_timeout);
 
-    // Start HTTP server for metrics endpoint
-    HttpServer::new(move || {
+    // Spawn textfile writer task, if enabled
+    if let Some(path) = &args.textfile_path {
+        tokio::spawn(textfile::textfile_writer(
+            path.clone(),
+            Duration::from_secs(args.textfile_interval),
+            app_state.clone(),
+        ));
+    }
+
+    // Spawn gRPC health server, if enabled
+    #[cfg(feature = "grpc-health")]
+    if let Some(port) = args.grpc_health_port {
+        let addr = (args.server_addr.parse::<std::net::IpAddr>()?, port).into();
+        let server = grpc_health::GrpcHealthServer::bind(addr).await?;
+        tokio::spawn(server.serve(app_state.clone()));
+    }
+
+    // Without the HTTP server, just keep the monitor running until shutdown
+    if args.no_http {
+        event!(Level::INFO, "HTTP server disabled, running monitor only");
+        shutdown.cancelled().await;
+    } else {
+        serve_http(&args, base_path, app_state.clone(), &shutdown).await?;
+    }
+
+    stop_monitor(connection_monitor, shutdown_timeout, &app_state.ws_endpoint).await;
+    if let Some(path) = &args.shutdown_report_path {
+        write_shutdown_report(path, &app_state).await?;
+    }
+
+    Ok(())
+}
+
+/// Serves the metrics, readiness, status and admin routes until shutdown.
+///
+/// # Arguments
+///
+/// * `args` - Monitor arguments (bind address, workers, admin routes)
+/// * `base_path` - Normalized path prefix all routes are mounted under
+/// * `app_state` - Shared application state the routes read from
+/// * `shutdown` - Token cancelled on shutdown, after which the server stops
+async fn serve_http(
+    args: &MonitorArgs,
+    base_path: String,
+    app_state: AppState,
+    shutdown: &CancellationToken,
+) -> Result<()> {
+    let enable_admin = args.enable_admin;
+    let app = move || {
+        let mut scope = web::scope(&base_path)
+            .service(metrics_handler)
+            .service(readiness_handler)
+            .service(status_handler);
+        if enable_admin {
+            scope = scope.service(pause_handler).service(resume_handler);
+        }
         App::new()
             .app_data(web::Data::new(app_state.clone()))
-            .service(metrics_handler)
-    })
-    .bind((args.server_addr, args.server_port))?
-    .run()
-    .await?;
+            .service(scope)
+    };
+
+    // Binding consumes the server, so a new one is built for every attempt
+    let mut retry_delay = HTTP_BIND_RETRY_DELAY;
+    let server = loop {
+        let result = HttpServer::new(app.clone())
+            .workers(args.http_workers as usize)
+            .bind((args.server_addr.as_str(), args.server_port));
+        match result {
+            Ok(server) => break server,
+            Err(e) if args.http_bind_retry => {
+                event!(
+                    Level::WARN,
+                    "Failed to bind HTTP server to {}:{}: {e}, retrying in {retry_delay:?}",
+                    args.server_addr,
+                    args.server_port
+                );
+                tokio::select! {
+                    _ = time::sleep(retry_delay) => {}
+                    _ = shutdown.cancelled() => return Ok(()),
+                }
+                retry_delay = (retry_delay * 2).min(MAX_HTTP_BIND_RETRY_DELAY);
+            }
+            Err(e) => return Err(e.into()),
+        }
+    };
+    server
+        .shutdown_signal(shutdown.clone().cancelled_owned())
+        .shutdown_timeout(args.shutdown_timeout)
+        .run()
+        .await?;
 
     Ok(())
 }
 
-/// Monitors WebSocket connection health by periodically connecting and making RPC calls.
+/// Runs a single check and reports the result in the Nagios plugin format.
 ///
-/// This function runs indefinitely, attempting to:
-/// 1. Establish a WebSocket connection to the node
-/// 2. Make an RPC call to fetch the finalized block head
-/// 3. Update success/failure counters based on the result
+/// # Returns
+///
+/// * `ExitCode::SUCCESS` (OK) if the check succeeded
+/// * Exit code 2 (CRITICAL) if the check failed
+async fn run_check(mut args: CheckArgs) -> Result<ExitCode> {
+    resolve_node_urls(&mut args)?;
+    check_node_urls(&args)?;
+
+    let transport = build_transport(&args)?;
+    let state = AppState::new(
+        &args,
+        HealthTracker::new(1, 1),
+        false,
+        Duration::ZERO,
+        false,
+        Arc::new(LogEventCounters::default()),
+        0,
+    )?;
+    let config = MonitorConfig::new(&args);
+    let endpoint = redact_url(&args.node_url);
+
+    match perform_check(&transport, &config, &state, &mut None).await {
+        Ok(()) => {
+            match state.chain.lock().unwrap().as_ref() {
+                Some(status) => println!("OK - {endpoint}: {status}"),
+                None => match config.check_mode {
+                    CheckMode::Rpc => println!("OK - {endpoint}: node answered"),
+                    CheckMode::Upgrade => println!("OK - {endpoint}: WebSocket upgrade succeeded"),
+                    CheckMode::Tcp => println!("OK - {endpoint}: TCP connection established"),
+                },
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        Err(error) => {
+            println!("CRITICAL - {endpoint}: {error}");
+            Ok(ExitCode::from(2))
+        }
+    }
+}
+
+/// Validates the monitor configuration without any network activity, printing
+/// every problem found.
 ///
 /// # Arguments
 ///
-/// * `url` - WebSocket URL of the node to monitor
-/// * `interval` - Seconds between connection attempts
-/// * `connection_timeout` - Timeout for establishing connection
-/// * `request_timeout` - Timeout for RPC requests
-/// * `success` - Atomic counter for successful checks
-/// * `failure` - Atomic counter for failed checks
-async fn connection_monitor(
-    url: String,
-    interval: u64,
-    connection_timeout: u64,
-    request_timeout: u64,
-    success: Arc<AtomicUsize>,
-    failure: Arc<AtomicUsize>,
-) {
-    let mut interval = time::interval(Duration::from_secs(interval));
-    let connection_timeout = Duration::from_secs(connection_timeout);
-    let request_timeout = Duration::from_secs(request_timeout);
+/// * `args` - Monitor arguments
+///
+/// # Returns
+///
+/// * `ExitCode::SUCCESS` if the configuration is valid, `ExitCode::FAILURE` otherwise
+fn validate_config(mut args: MonitorArgs) -> ExitCode {
+    let mut problems = Vec::new();
+    let mut check = |result: Result<()>| {
+        if let Err(error) = result {
+            problems.push(format!("{error:#}"));
+        }
+    };
 
-    loop {
-        interval.tick().await;
+    // URLs are only validated once they could be resolved
+    match resolve_node_urls(&mut args.check) {
+        Ok(()) => check(check_node_urls(&args.check)),
+        Err(error) => check(Err(error)),
+    }
+    check(build_transport(&args.check).map(drop));
+    check(normalize_base_path(&args.base_path).map(drop));
+    if let Some(threshold) = args.degraded_latency_threshold {
+        check(
+            Duration::try_from_secs_f64(threshold)
+                .map(drop)
+                .context("Invalid --degraded-latency-threshold"),
+        );
+    }
+    if let Some(buckets) = &args.latency_buckets {
+        check(
+            check_latency_histogram("", buckets)
+                .map(drop)
+                .context("Invalid --latency-buckets"),
+        );
+    }
+    if let Some(path) = &args.admin_token_file {
+        check(read_admin_token(path).map(drop));
+    }
 
-        // Attempt to connect to the node
-        match WsClientBuilder::new()
-            .connection_timeout(connection_timeout)
-            .request_timeout(request_timeout)
-            .build(&url)
-            .await
-        {
-            Ok(client) => {
-                // Connection established, attempt to get the finalized block head
-                match client
-                    .request::<String, _>("chain_getFinalizedHead", rpc_params![])
-                    .await
-                {
-                    Ok(resp) => {
-                        // Success: valid response received
-                        event!(Level::DEBUG, "Successful check, finalized head: {resp}");
-                        success.fetch_add(1, Ordering::Relaxed);
-                    }
-                    Err(e) => {
-                        // Failure: RPC request failed
-                        event!(Level::WARN, "Check failed during RPC request: {e}");
-                        failure.fetch_add(1, Ordering::Relaxed);
-                    }
+    if problems.is_empty() {
+        println!("Configuration is valid");
+        return ExitCode::SUCCESS;
+    }
+    println!("Configuration is invalid:");
+    for problem in &problems {
+        println!("  - {problem}");
+    }
+    ExitCode::FAILURE
+}
+
+/// Builds the WebSocket transport from the TLS and subprotocol arguments.
+fn build_transport(args: &CheckArgs) -> Result<Transport> {
+    Transport::new(TransportOptions {
+        subprotocol: args.ws_subprotocol.clone(),
+        client_identity: args
+            .tls_client_cert
+            .clone()
+            .zip(args.tls_client_key.clone())
+            .map(|(cert, key)| ClientIdentity { cert, key }),
+        rpc_version: args.rpc_version,
+        auth_token_file: args.auth_token_file.clone(),
+        tls_sni: args.tls_sni.clone(),
+        tls_pins: args.tls_pins.clone(),
+    })
+}
+
+/// Returns the node's URLs in the order they are tried, primary first.
+fn node_urls(args: &CheckArgs) -> Vec<String> {
+    std::iter::once(args.node_url.clone())
+        .chain(args.fallback_url.iter().cloned())
+        .collect()
+}
+
+/// Waits for SIGINT or SIGTERM and cancels the shutdown token.
+async fn wait_for_shutdown_signal(shutdown: CancellationToken) {
+    let ctrl_c = tokio::signal::ctrl_c();
+
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = ctrl_c => {}
+                    _ = terminate.recv() => {}
                 }
             }
             Err(e) => {
-                // Failure: could not establish connection
-                event!(Level::WARN, "Check failed during connection: {e}");
-                failure.fetch_add(1, Ordering::Relaxed);
+                event!(Level::WARN, "Failed to listen for SIGTERM: {e}");
+                let _ = ctrl_c.await;
             }
         }
     }
+
+    #[cfg(not(unix))]
+    let _ = ctrl_c.await;
+
+    event!(Level::INFO, "Shutdown signal received, stopping");
+    shutdown.cancel();
 }
 
-/// HTTP handler for the `/metrics` endpoint.
+/// Summary of a monitor run, written on shutdown with `--shutdown-report-path`.
+#[derive(Serialize)]
+struct ShutdownReport {
+    /// The WebSocket endpoint being monitored (redacted).
+    endpoint: String,
+    /// Time since the monitor started in seconds.
+    uptime_seconds: f64,
+    /// Number of completed checks.
+    checks: usize,
+    /// Number of successful checks.
+    successes: usize,
+    /// Number of failed checks.
+    failures: usize,
+    /// Failed checks by reason, as in `check_error_total`.
+    failures_by_reason: BTreeMap<&'static str, usize>,
+    /// Whether the endpoint was considered up at shutdown.
+    up: bool,
+    /// Most recent failures, oldest first.
+    recent_errors: Vec<ErrorRecord>,
+}
+
+/// Writes the summary of the monitor run to `path` as JSON, atomically.
 ///
-/// Returns Prometheus-formatted metrics showing the current success and failure counts
-/// for the monitored WebSocket endpoint.
-#[get("/metrics")]
-async fn metrics_handler(data: web::Data<AppState>) -> HttpResponse {
-    let success = data.success.load(Ordering::Relaxed);
-    let failure = data.failure.load(Ordering::Relaxed);
+/// # Arguments
+///
+/// * `path` - Destination file of the report
+/// * `state` - Shared application state holding the check counters
+async fn write_shutdown_report(path: &Path, state: &AppState) -> Result<()> {
+    let successes = state.success.load(Ordering::Relaxed);
+    let failures = state.failure.load(Ordering::Relaxed);
+    let report = ShutdownReport {
+        endpoint: state.ws_endpoint.clone(),
+        uptime_seconds: state.started.elapsed().as_secs_f64(),
+        checks: successes + failures,
+        successes,
+        failures,
+        failures_by_reason: state
+            .errors
+            .snapshot()
+            .into_iter()
+            .map(|(reason, count)| (reason.as_str(), count))
+            .collect(),
+        up: state.health.is_up(),
+        recent_errors: state.recent_errors.snapshot(),
+    };
+    textfile::write_atomically(path, &serde_json::to_vec_pretty(&report)?)
+        .await
+        .with_context(|| format!("Failed to write shutdown report to '{}'", path.display()))?;
+    event!(Level::INFO, "Wrote shutdown report to {}", path.display());
 
-    prometheus_output(&data.ws_endpoint, success, failure)
+    Ok(())
 }
 
-/// Generates Prometheus-formatted metrics output.
+/// Waits for the connection monitor to finish its in-flight check, aborting it
+/// once the shutdown timeout is exceeded.
+///
+/// # Arguments
+///
+/// * `monitor` - Handle of the connection monitor task
+/// * `timeout` - Maximum time to wait for the task to finish
+/// * `endpoint` - The WebSocket endpoint being monitored (used in the log message)
+async fn stop_monitor(mut monitor: JoinHandle<()>, timeout: Duration, endpoint: &str) {
+    if time::timeout(timeout, &mut monitor).await.is_err() {
+        event!(
+            Level::WARN,
+            "Shutdown timeout of {timeout:?} exceeded, aborting in-flight check of {endpoint}"
+        );
+        monitor.abort();
+    }
+}
+
+/// Monitors WebSocket connection health by periodically connecting and making RPC calls.
+///
+/// This function runs until shutdown, attempting to:
+/// 1. Establish a WebSocket connection to the node (or reuse it in persistent mode)
+/// 2. Make RPC calls to fetch the finalized block and sync state
+/// 3. Update success/failure counters based on the result
 ///
-/// Creates counter metrics with appropriate labels and returns them as an HTTP response
-/// with the correct content type for Prometheus scraping.
+/// URLs are tried in order, primary first, until one of them succeeds. The check
+/// only fails if all of them fail.
 ///
 /// # Arguments
 ///
-/// * `endpoint` - The WebSocket endpoint being monitored (used as label)
-/// * `success` - Current success count
-/// * `failure` - Current failure count
-fn prometheus_output(endpoint: &str, success: usize, failure: usize) -> HttpResponse {
-    // Create counter metrics with endpoint label
-    let counter_opts =
-        Opts::new("check_count", "Counter for checks results").const_label("endpoint", endpoint);
-    let success_counter =
-        Counter::with_opts(counter_opts.clone().const_label("result", "SUCCESS")).unwrap();
-    let failure_counter =
-        Counter::with_opts(counter_opts.const_label("result", "TIMEOUT")).unwrap();
+/// * `config` - Check settings (URLs, timeouts, RPC preset)
+/// * `interval` - Interval between checks outside of the check profiles' windows
+/// * `transport` - Transport used to establish WebSocket connections
+/// * `state` - Shared application state holding the check counters
+/// * `hook` - Command run after each check, if configured
+/// * `statsd` - StatsD sink the check results are pushed to, if configured
+/// * `shutdown` - Token cancelled on shutdown, after which no new check is started
+async fn connection_monitor(
+    config: MonitorConfig,
+    interval: Duration,
+    transport: Transport,
+    state: AppState,
+    hook: Option<ResultHook>,
+    statsd: Option<StatsdSink>,
+    shutdown: CancellationToken,
+) {
+    let mut schedule = time::interval(interval);
 
-    // Create and populate registry
-    let r = Registry::new();
-    r.register(Box::new(success_counter.clone())).unwrap();
-    r.register(Box::new(failure_counter.clone())).unwrap();
+    // Check profile whose interval the schedule currently follows
+    let mut profile = None;
 
-    // Set counter values
-    success_counter.inc_by(success as f64);
-    failure_counter.inc_by(failure as f64);
+    // Factor the interval is stretched by after consecutive rate limited checks
+    let mut backoff = 1;
 
-    // Encode metrics to Prometheus text format
-    let mut buffer = vec![];
-    let encoder = TextEncoder::new();
-    let metric_families = r.gather();
-    encoder.encode(&metric_families, &mut buffer).unwrap();
+    // Live connection kept between checks in persistent mode, with the index of its URL
+    let mut active: Option<(usize, WsClient)> = None;
 
-    // Return metrics with appropriate content type
-    HttpResponse::Ok()
-        .content_type(encoder.format_type())
-        .body(buffer)
+    // Sequence number of the check, for correlating its log events
+    let mut sequence: u64 = 0;
+
+    // End of the last check or keepalive call on the persistent connection
+    let mut last_activity = Instant::now();
+
+    loop {
+        let keepalive_at = config
+            .idle_keepalive
+            .filter(|_| active.is_some())
+            .map(|idle| last_activity + idle);
+        let keepalive = async move {
+            match keepalive_at {
+                Some(at) => time::sleep_until(at).await,
+                None => std::future::pending().await,
+            }
+        };
+        let scheduled = tokio::select! {
+            scheduled = schedule.tick() => scheduled,
+            _ = keepalive => {
+                keep_alive(&config, &state, &mut active).await;
+                last_activity = Instant::now();
+                continue;
+            }
+            _ = shutdown.cancelled() => break,
+        };
+
+        // Paused checks are skipped, keeping the last results until resumed
+        if state.paused.load(Ordering::Relaxed) {
+            continue;
+        }
+
+        // Ticks missed while a check ran long fire late, revealing scheduler lag
+        let delay = scheduled.elapsed();
+        *state.schedule_delay.lock().unwrap() = Some(delay);
+
+        // Missed ticks are made up in a burst, so every further period of delay is
+        // another check waiting to start right after this one
+        let pending = delay.as_nanos() / schedule.period().as_nanos();
+        state
+            .pending_checks
+            .store(pending as usize, Ordering::Relaxed);
+
+        // Switch the interval when entering or leaving a profile's window
+        let current = check_profile::active(&config.check_profiles, SystemTime::now());
+        if current != profile {
+            let period = current.map_or(interval, |current| current.interval);
+            match current {
+                Some(current) => event!(
+                    Level::INFO,
+                    "Check profile {current} active, checking every {period:?}"
+                ),
+                None => event!(
+                    Level::INFO,
+                    "No check profile active, checking every {period:?}"
+                ),
+            }
+            schedule = time::interval_at(Instant::now() + period, period);
+            state.pending_checks.store(0, Ordering::Relaxed);
+            profile = current;
+        }
+
+        sequence += 1;
+        let span = span!(
+            Level::INFO,
+            "check",
+            endpoint = state.ws_endpoint,
+            seq = sequence
+        );
+        let started = Instant::now();
+        let result = perform_check(&transport, &config, &state, &mut active)
+            .instrument(span)
+            .await;
+        let duration = started.elapsed();
+        last_activity = Instant::now();
+        if let Some(hook) = &hook {
+            let chain = state
+                .node_info
+                .lock()
+                .unwrap()
+                .clone()
+                .and_then(|info| info.chain);
+            hook.run(
+                &state.ws_endpoint,
+                &result.clone().map_err(|error| error.message),
+                duration,
+                state.health.is_up(),
+                chain.as_deref(),
+            );
+        }
+        if let Some(statsd) = &statsd {
+            statsd
+                .send_check(result.is_ok(), duration, state.health.is_up())
+                .await;
+        }
+
+        // Delay until the next check, unless stretched below
+        let mut next_delay = schedule.period();
+
+        // Back off exponentially instead of hammering a throttling gateway
+        if config.rate_limit_backoff {
+            let rate_limited = result
+                .as_ref()
+                .is_err_and(|error| error.reason == ErrorReason::RateLimited);
+            if rate_limited {
+                backoff = (backoff * 2).min(MAX_RATE_LIMIT_BACKOFF);
+                let delay = schedule.period() * backoff;
+                event!(
+                    Level::INFO,
+                    "Rate limited, delaying the next check by {delay:?}"
+                );
+                schedule.reset_after(delay);
+                next_delay = delay;
+            } else {
+                backoff = 1;
+            }
+        }
+
+        // Wait once as long as the server asked, if longer than planned anyway
+        let retry_after = state.retry_after.lock().unwrap().take();
+        if let Some(retry_after) = retry_after.filter(|_| config.honor_retry_after) {
+            let delay = retry_after.min(MAX_RETRY_AFTER);
+            if delay > next_delay {
+                event!(
+                    Level::INFO,
+                    "Server asked to retry after {retry_after:?}, delaying the next check"
+                );
+                schedule.reset_after(delay);
+                state.retry_after_honored.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Makes a keepalive call on the idle persistent connection.
+///
+/// A failed call drops the connection, so the next check establishes a new one
+/// instead of failing on a dead connection.
+///
+/// # Arguments
+///
+/// * `config` - Check settings (RPC preset)
+/// * `state` - Shared application state holding the keepalive counter
+/// * `active` - Live connection kept between checks, with the index of its URL
+async fn keep_alive(
+    config: &MonitorConfig,
+    state: &AppState,
+    active: &mut Option<(usize, WsClient)>,
+) {
+    let Some((_, client)) = active else {
+        return;
+    };
+    state.keepalives.fetch_add(1, Ordering::Relaxed);
+    if let Err(e) = config.chain_type.keepalive(client).await {
+        event!(
+            Level::WARN,
+            "Keepalive call failed, dropping the connection: {e}"
+        );
+        *active = None;
+        state.connections.lost();
+    }
 }
 
-/// Validates that the provided node URL is a valid WebSocket URL.
+/// Performs a single check, trying the node's URLs in order until one succeeds.
 ///
 /// # Arguments
 ///
-/// * `node_url` - The URL string to validate
+/// * `transport` - Transport used to establish WebSocket connections
+/// * `config` - Check settings (URLs, timeouts, RPC preset)
+/// * `state` - Shared application state holding the check counters
+/// * `active` - Live connection kept between checks in persistent mode, with the
+///   index of its URL
 ///
 /// # Returns
 ///
-/// * `Ok(())` if the URL is valid
-/// * `Err` if the URL cannot be parsed or uses an unsupported scheme
-fn check_node_url(node_url: &str) -> Result<()> {
-    let parsed = Url::parse(node_url)?;
-    let scheme = parsed.scheme();
-    match scheme {
-        "ws" | "wss" => {}
-        _ => {
-            bail!("Unsupported URL scheme '{}'", scheme);
-        }
+/// * `Ok(())` if any URL succeeded
+/// * `Err(CheckError)` with the error of the last URL if all of them failed
+async fn perform_check(
+    transport: &Transport,
+    config: &MonitorConfig,
+    state: &AppState,
+    active: &mut Option<(usize, WsClient)>,
+) -> Result<(), CheckError> {
+    // Start of the check, for the total duration including connection setup
+    let started = Instant::now();
+
+    if active
+        .as_ref()
+        .is_some_and(|(_, client)| !client.is_connected())
+    {
+        *active = None;
+        state.connections.lost();
     }
 
-    Ok(())
+    // Reconnect proactively so a DNS load balancer can pick another backend
+    if active.is_some()
+        && config
+            .connection_max_age
+            .zip(state.connections.age())
+            .is_some_and(|(max_age, age)| age >= max_age)
+    {
+        event!(
+            Level::DEBUG,
+            "Connection exceeded the maximum age, reconnecting"
+        );
+        *active = None;
+        state.connections.expired();
+    }
+
+    let mut result = Ok(());
+    let mut attempts = Vec::new();
+    for (index, url) in config.urls.iter().enumerate() {
+        // Reuse the live connection if it belongs to this URL
+        let reused = match active.take() {
+            Some((i, client)) if i == index => Some(client),
+            other => {
+                *active = other;
+                None
+            }
+        };
+
+        let reusing = reused.is_some();
+        let (url_result, client, mut measured) =
+            check_url(transport, config, state, url, reused).await;
+        measured.rate_limited = url_result
+            .as_ref()
+            .is_err_and(|error| error.reason == ErrorReason::RateLimited);
+        attempts.push((index, url_result.is_ok(), measured));
+        // Checks over HTTP fallback or after transport errors leave no connection to keep
+        if let Some(client) = client.filter(|_| config.persistent) {
+            *active = Some((index, client));
+        } else if reusing {
+            state.connections.lost();
+        }
+        match url_result {
+            Ok(()) => {
+                result = Ok(());
+                break;
+            }
+            Err(error) => result = Err(error),
+        }
+    }
+
+    state.record_check(attempts, &result, started.elapsed());
+
+    if active.is_none() {
+        state.connections.dropped();
+    }
+
+    result
+}
+
+/// Result of a check, exposed under blackbox_exporter's metric names.
+#[derive(Clone, Copy)]
+struct Probe {
+    /// Whether the check succeeded.
+    success: bool,
+    /// Total duration of the check.
+    duration: Duration,
+    /// DNS resolution time of the check's last connection, zero if there was none.
+    dns_lookup: Duration,
+}
+
+/// Values measured while checking a single URL, recorded once the whole check is done.
+#[derive(Default)]
+struct UrlCheck {
+    /// Results of the status RPC sub-calls that were made.
+    calls: Vec<(StatusCall, bool)>,
+    /// RPC round-trip time, if the node answered the status calls.
+    latency: Option<f64>,
+    /// Chain status reported by the node, if it answered the status calls.
+    status: Option<ChainStatus>,
+    /// Whether the check was made over HTTP after the upgrade was rejected.
+    http_fallback: bool,
+    /// Whether the check reused a connection made by an earlier check.
+    warm: bool,
+    /// Whether the check failed because the gateway throttled the monitor.
+    rate_limited: bool,
+    /// Delay the server asked for before the next attempt, e.g. via `Retry-After`.
+    retry_after: Option<Duration>,
+    /// Whether an RPC request failed on the transport level, e.g. with a timeout,
+    /// so the connection cannot be trusted anymore.
+    transport_failed: bool,
+    /// Durations of the phases of the connection made by the check, if any.
+    timings: ConnectTimings,
+    /// TLS certificate expiry (Unix timestamp) seen on a new `wss://` connection.
+    cert_expiry: Option<i64>,
+}
+
+/// Checks a single URL, connecting to it unless a live client is passed in.
+///
+/// Failures are logged and returned as the full error chain, as the caller may
+/// try a fallback URL. Nothing is recorded into the state's check counters; the
+/// measurements are returned for the caller to record with the whole check.
+///
+/// # Arguments
+///
+/// * `transport` - Transport used to establish the WebSocket connection
+/// * `config` - Monitor settings (timeouts, RPC preset)
+/// * `state` - Shared application state holding the check counters
+/// * `url` - WebSocket URL to check
+/// * `reused` - Live client connected to `url`, if there is one
+///
+/// # Returns
+///
+/// The result of the check, `Ok(())` or `Err(CheckError)` with the error chain,
+/// along with
+/// * the client if its connection is still usable and may be kept for the next
+///   check, i.e. unless establishing it or a request failed on the transport
+///   level, or the check was made over HTTP fallback or without RPC calls
+/// * the values measured during the check
+async fn check_url(
+    transport: &Transport,
+    config: &MonitorConfig,
+    state: &AppState,
+    url: &str,
+    reused: Option<WsClient>,
+) -> (Result<(), CheckError>, Option<WsClient>, UrlCheck) {
+    // Credentials must not leak into logs
+    let redacted = redact_url(url);
+    let new_connection = reused.is_none();
+
+    if config.check_mode != CheckMode::Rpc {
+        let (result, measured) = check_connectivity(transport, config, url, &redacted).await;
+        return (result, None, measured);
+    }
+
+    let mut connected = UrlCheck::default();
+    let client = match reused {
+        Some(client) => client,
+        None => match connect(transport, config, url, &mut connected).await {
+            Ok(client) => {
+                if config.persistent {
+                    state.connections.established();
+                }
+                client
+            }
+            Err(e)
+                if config.allow_http_fallback
+                    && e.chain().any(|cause| {
+                        // Throttled upgrades would only be throttled over HTTP as well
+                        cause
+                            .downcast_ref::<UpgradeRejected>()
+                            .is_some_and(|rejected| !rejected.is_rate_limited())
+                    }) =>
+            {
+                event!(
+                    Level::DEBUG,
+                    url = redacted,
+                    error = error_chain(e.as_ref()),
+                    "WebSocket upgrade rejected, retrying over HTTP"
+                );
+                let (result, measured) =
+                    check_over_http(transport, config, state, url, &redacted).await;
+                let measured = UrlCheck {
+                    timings: connected.timings,
+                    ..measured
+                };
+                return (result, None, measured);
+            }
+            Err(e) => {
+                // Failure: could not establish connection
+                let error = CheckError::from_error(e.as_ref());
+                match error.reason {
+                    ErrorReason::TlsDowngrade => event!(
+                        Level::WARN,
+                        url = redacted,
+                        error = error.message,
+                        "Check failed: TLS downgrade detected, the endpoint may be misconfigured or intercepted"
+                    ),
+                    ErrorReason::CertPinMismatch => event!(
+                        Level::WARN,
+                        url = redacted,
+                        error = error.message,
+                        "Check failed: TLS certificate does not match any pin, the endpoint may be intercepted or its certificate rotated"
+                    ),
+                    _ => event!(
+                        Level::WARN,
+                        url = redacted,
+                        error = error.message,
+                        "Check failed during connection"
+                    ),
+                }
+                let measured = UrlCheck {
+                    retry_after: retry_after(&e),
+                    ..connected
+                };
+                return (Err(error), None, measured);
+            }
+        },
+    };
+
+    let (result, measured) = check_client(config, state, &client, &redacted, new_connection).await;
+    let measured = UrlCheck {
+        timings: connected.timings,
+        cert_expiry: connected.cert_expiry,
+        ..measured
+    };
+    // Failed checks whose connection is fine, e.g. due to too few peers, keep it
+    let client = (!measured.transport_failed).then_some(client);
+    (result, client, measured)
+}
+
+/// Checks that a URL accepts connections, without any RPC call.
+///
+/// Depending on the check mode, only a TCP connection is opened or the WebSocket
+/// connection is established, and closed right away.
+///
+/// # Arguments
+///
+/// * `transport` - Transport used to establish the connection
+/// * `config` - Monitor settings (timeouts, check mode)
+/// * `url` - WebSocket URL to check
+/// * `redacted` - Redacted URL of the node, for logging
+async fn check_connectivity(
+    transport: &Transport,
+    config: &MonitorConfig,
+    url: &str,
+    redacted: &str,
+) -> (Result<(), CheckError>, UrlCheck) {
+    let mut measured = UrlCheck::default();
+    let result = match config.check_mode {
+        CheckMode::Tcp => connect_tcp(transport, config, url, &mut measured.timings).await,
+        _ => connect(transport, config, url, &mut measured)
+            .await
+            .map(drop),
+    };
+    match result {
+        Ok(()) => (Ok(()), measured),
+        Err(e) => {
+            let error = CheckError::from_error(e.as_ref());
+            event!(
+                Level::WARN,
+                url = redacted,
+                error = error.message,
+                "Check failed during connection"
+            );
+            let measured = UrlCheck {
+                retry_after: retry_after(&e),
+                ..UrlCheck::default()
+            };
+            (Err(error), measured)
+        }
+    }
+}
+
+/// Returns the delay a rejected WebSocket upgrade asked for before the next attempt.
+fn retry_after(error: &anyhow::Error) -> Option<Duration> {
+    error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<UpgradeRejected>())
+        .and_then(|rejected| rejected.retry_after)
+}
+
+/// Checks a URL over HTTP JSON-RPC, after its WebSocket upgrade was rejected.
+///
+/// # Arguments
+///
+/// * `transport` - Transport providing the bearer token, if configured
+/// * `config` - Monitor settings (timeouts, RPC preset)
+/// * `state` - Shared application state holding the check counters
+/// * `url` - WebSocket URL to check, converted to the equivalent HTTP URL
+/// * `redacted` - Redacted URL of the node, for logging
+async fn check_over_http(
+    transport: &Transport,
+    config: &MonitorConfig,
+    state: &AppState,
+    url: &str,
+    redacted: &str,
+) -> (Result<(), CheckError>, UrlCheck) {
+    let client = http_client(transport, config, url).await;
+    let measured = UrlCheck {
+        http_fallback: true,
+        ..UrlCheck::default()
+    };
+    let client = match client {
+        Ok(client) => client,
+        Err(e) => {
+            let error = CheckError::from_error(e.as_ref());
+            event!(
+                Level::WARN,
+                url = redacted,
+                error = error.message,
+                "Check failed during HTTP fallback"
+            );
+            return (Err(error), measured);
+        }
+    };
+
+    // Every request is independent, so attributes are discovered until known
+    let discover = state.node_info.lock().unwrap().is_none();
+    let (result, measured) = check_client(config, state, &client, redacted, discover).await;
+    (
+        result,
+        // A fresh HTTP client is built for every check
+        UrlCheck {
+            http_fallback: true,
+            warm: false,
+            ..measured
+        },
+    )
+}
+
+/// Builds the HTTP JSON-RPC client for a WebSocket URL, with the bearer token if configured.
+async fn http_client(
+    transport: &Transport,
+    config: &MonitorConfig,
+    url: &str,
+) -> Result<HttpClient> {
+    let mut headers = HeaderMap::new();
+    if let Some(token) = transport.auth_token().await? {
+        headers.insert(
+            "authorization",
+            HeaderValue::from_str(&format!("Bearer {token}"))?,
+        );
+    }
+
+    Ok(HttpClientBuilder::default()
+        .request_timeout(config.request_timeout)
+        .set_headers(headers)
+        .build(http_url(url)?)?)
+}
+
+/// Checks a node through a connected client, once the connection is established.
+///
+/// # Arguments
+///
+/// * `config` - Monitor settings (RPC preset, peer minimum)
+/// * `state` - Shared application state holding the check counters
+/// * `client` - Client connected to the node
+/// * `redacted` - Redacted URL of the node, for logging
+/// * `new_connection` - Whether the client was just connected
+async fn check_client(
+    config: &MonitorConfig,
+    state: &AppState,
+    client: &impl ClientT,
+    redacted: &str,
+    new_connection: bool,
+) -> (Result<(), CheckError>, UrlCheck) {
+    // Attempt to get the finalized block and sync state
+    let request_started = Instant::now();
+    let cache = config
+        .cache_finalized_number
+        .then_some(state.finalized.as_ref());
+    let (result, calls) = config
+        .chain_type
+        .fetch_status(client, cache, config.rpc_quorum)
+        .await;
+    let result = match result {
+        Ok(status) if config.pinned_head_check => config
+            .chain_type
+            .verify_pinned_head(client, &status)
+            .await
+            .map(|()| status),
+        result => result,
+    };
+    let result = match result {
+        Ok(mut status) if config.runtime_version => {
+            match config.chain_type.fetch_spec_version(client).await {
+                Ok(spec_version) => {
+                    status.spec_version = spec_version;
+                    Ok(status)
+                }
+                // Without a minimum, the spec version only enriches metrics
+                Err(e) if config.min_spec_version.is_none() => {
+                    state.record_deserialization_error(redacted, &e);
+                    event!(
+                        Level::DEBUG,
+                        url = redacted,
+                        error = error_chain(&e),
+                        "Could not fetch the runtime version"
+                    );
+                    Ok(status)
+                }
+                Err(e) => Err(e),
+            }
+        }
+        result => result,
+    };
+    let mut measured = UrlCheck {
+        calls,
+        warm: !new_connection,
+        ..UrlCheck::default()
+    };
+    let latency = request_started.elapsed().as_secs_f64();
+    if let Err(e) = &result {
+        state.record_deserialization_error(redacted, e);
+    }
+    match result {
+        Ok(status)
+            if status
+                .peers
+                .zip(config.min_peers)
+                .is_some_and(|(peers, min_peers)| peers < min_peers) =>
+        {
+            // Failure: the node answered, but cannot follow the chain reliably
+            let error = CheckError::new(
+                ErrorReason::LowPeers,
+                format!(
+                    "Too few peers: {} (minimum {})",
+                    status.peers.unwrap_or_default(),
+                    config.min_peers.unwrap_or_default()
+                ),
+            );
+            event!(
+                Level::WARN,
+                url = redacted,
+                error = error.message,
+                "Check failed due to low peer count"
+            );
+            measured.latency = Some(latency);
+            measured.status = Some(status);
+            return (Err(error), measured);
+        }
+        Ok(status)
+            if status
+                .spec_version
+                .zip(config.min_spec_version)
+                .is_some_and(|(spec_version, min)| spec_version < min) =>
+        {
+            // Failure: the node answered, but missed a runtime upgrade
+            let error = CheckError::new(
+                ErrorReason::OutdatedRuntime,
+                format!(
+                    "Outdated runtime: spec version {} (minimum {})",
+                    status.spec_version.unwrap_or_default(),
+                    config.min_spec_version.unwrap_or_default()
+                ),
+            );
+            event!(
+                Level::WARN,
+                url = redacted,
+                error = error.message,
+                "Check failed due to outdated runtime"
+            );
+            measured.latency = Some(latency);
+            measured.status = Some(status);
+            return (Err(error), measured);
+        }
+        Ok(mut status) => {
+            // Success: valid responses received
+            if config.finality_gap {
+                fetch_finality_gap(config, state, client, redacted, &mut status).await;
+            }
+            if config.block_age {
+                fetch_block_timestamp(config, state, client, redacted, &mut status).await;
+            }
+            event!(
+                Level::DEBUG,
+                url = redacted,
+                "Successful check, {status} (hash: {:?})",
+                status.finalized_hash
+            );
+            measured.status = Some(status);
+            if config.discover_node_info && new_connection {
+                discover_node_info(config, state, client, redacted).await;
+            }
+            if config.detect_response_changes {
+                detect_response_changes(config, state, client, redacted).await;
+            }
+            if !config.custom_gauges.is_empty() {
+                update_custom_gauges(config, state, client, redacted).await;
+            }
+        }
+        Err(e) if config.ignore_rpc_errors && is_rpc_error(&e) => {
+            // Success: the node answered, only the RPC call itself failed
+            event!(
+                Level::DEBUG,
+                url = redacted,
+                error = error_chain(&e),
+                "Successful check, ignoring RPC error"
+            );
+        }
+        Err(e) => {
+            // Failure: RPC request failed. The connection is only replaced after
+            // transport errors, as error responses come over a working connection.
+            measured.transport_failed = !is_rpc_error(&e);
+            let error = CheckError::from_error(&e);
+            event!(
+                Level::WARN,
+                url = redacted,
+                error = error.message,
+                "Check failed during RPC request"
+            );
+            return (Err(error), measured);
+        }
+    }
+    measured.latency = Some(latency);
+
+    (Ok(()), measured)
+}
+
+/// Fetches the chain name and node version for the `endpoint_info` metric, and
+/// detects whether the node is an archive node if not known yet.
+///
+/// Failing to fetch them does not fail the check; the previously discovered
+/// attributes are kept.
+///
+/// # Arguments
+///
+/// * `config` - Monitor settings (RPC preset)
+/// * `state` - Shared application state holding the node attributes
+/// * `client` - Client connected to the node
+/// * `url` - Redacted URL of the node, for logging
+async fn discover_node_info(
+    config: &MonitorConfig,
+    state: &AppState,
+    client: &impl ClientT,
+    url: &str,
+) {
+    match config.chain_type.fetch_info(client).await {
+        Ok(info) => {
+            // Lean endpoints lack these methods for good, so only mention it once
+            let first = state.node_info.lock().unwrap().is_none();
+            if first && (info.chain.is_none() || info.version.is_none()) {
+                event!(
+                    Level::DEBUG,
+                    url,
+                    "Node does not implement all attribute methods, reporting them as unknown"
+                );
+            }
+            event!(
+                Level::DEBUG,
+                url,
+                chain = info.chain,
+                version = info.version,
+                "Discovered node attributes"
+            );
+            *state.node_info.lock().unwrap() = Some(info);
+        }
+        Err(e) => {
+            state.record_deserialization_error(url, &e);
+            event!(
+                Level::DEBUG,
+                url,
+                error = error_chain(&e),
+                "Could not fetch node attributes"
+            );
+        }
+    }
+
+    // Pruning does not change while the node runs, so detection is not repeated
+    if state.archive.lock().unwrap().is_some() {
+        return;
+    }
+    match config.chain_type.fetch_is_archive(client).await {
+        Ok(archive) => {
+            event!(Level::DEBUG, url, archive, "Detected archive mode");
+            *state.archive.lock().unwrap() = Some(archive);
+        }
+        Err(e) => {
+            state.record_deserialization_error(url, &e);
+            event!(
+                Level::DEBUG,
+                url,
+                error = error_chain(&e),
+                "Could not detect archive mode"
+            );
+        }
+    }
+}
+
+/// Fetches the distance between the best and the finalized head into `status`.
+///
+/// Failing to fetch it does not fail the check, the gap is then left unknown.
+///
+/// # Arguments
+///
+/// * `config` - Monitor settings (RPC preset, caching)
+/// * `state` - Shared application state holding the best head cache
+/// * `client` - Client connected to the node
+/// * `url` - Redacted URL of the node, for logging
+/// * `status` - Status reported by the check, including the finalized block number
+async fn fetch_finality_gap(
+    config: &MonitorConfig,
+    state: &AppState,
+    client: &impl ClientT,
+    url: &str,
+    status: &mut ChainStatus,
+) {
+    let cache = config.cache_finalized_number.then_some(state.best.as_ref());
+    match config
+        .chain_type
+        .fetch_finality_gap(client, status, cache)
+        .await
+    {
+        Ok(gap) => status.finality_gap_blocks = gap,
+        Err(e) => {
+            state.record_deserialization_error(url, &e);
+            event!(
+                Level::DEBUG,
+                url,
+                error = error_chain(&e),
+                "Could not fetch the best head"
+            );
+        }
+    }
+}
+
+/// Fetches the timestamp of the block reported by the check, for its age.
+///
+/// Failing to fetch it does not fail the check, as the node already answered the
+/// status requests; the age is then not exposed.
+///
+/// # Arguments
+///
+/// * `config` - Monitor settings (RPC preset)
+/// * `state` - Shared application state, for counting deserialization failures
+/// * `client` - Client connected to the node
+/// * `url` - Redacted URL of the node, for logging
+/// * `status` - Status reported by the check, including the finalized block
+async fn fetch_block_timestamp(
+    config: &MonitorConfig,
+    state: &AppState,
+    client: &impl ClientT,
+    url: &str,
+    status: &mut ChainStatus,
+) {
+    match config
+        .chain_type
+        .fetch_block_timestamp(client, status)
+        .await
+    {
+        Ok(timestamp) => status.block_timestamp_ms = timestamp,
+        Err(e) => {
+            state.record_deserialization_error(url, &e);
+            event!(
+                Level::DEBUG,
+                url,
+                error = error_chain(&e),
+                "Could not fetch the block timestamp"
+            );
+        }
+    }
+}
+
+/// Fetches the chain identity fields and logs any that changed since the previous check.
+///
+/// Failing to fetch the fields does not fail the check, as the node already answered
+/// the status requests.
+///
+/// # Arguments
+///
+/// * `config` - Monitor settings (RPC preset)
+/// * `state` - Shared application state holding the last field values
+/// * `client` - Client connected to the node
+/// * `url` - Redacted URL of the node, for logging
+async fn detect_response_changes(
+    config: &MonitorConfig,
+    state: &AppState,
+    client: &impl ClientT,
+    url: &str,
+) {
+    match config.chain_type.fetch_identity(client).await {
+        Ok(fields) => {
+            for change in state.responses.observe(fields) {
+                event!(
+                    Level::INFO,
+                    url,
+                    field = change.field,
+                    before = change.before,
+                    after = change.after,
+                    "Unexpected change in node response"
+                );
+            }
+        }
+        Err(e) => {
+            state.record_deserialization_error(url, &e);
+            event!(
+                Level::DEBUG,
+                url,
+                error = error_chain(&e),
+                "Could not fetch chain identity fields"
+            );
+        }
+    }
+}
+
+/// Updates the `--custom-gauge` gauges from their RPC responses.
+///
+/// Failed calls and missing or non-numeric fields leave the gauge unchanged and
+/// do not fail the check.
+///
+/// # Arguments
+///
+/// * `config` - Monitor settings (custom gauges)
+/// * `state` - Shared application state holding the gauge values
+/// * `client` - Client connected to the node
+/// * `url` - Redacted URL of the node, for logging
+async fn update_custom_gauges(
+    config: &MonitorConfig,
+    state: &AppState,
+    client: &impl ClientT,
+    url: &str,
+) {
+    for gauge in &config.custom_gauges {
+        match gauge.fetch(client).await {
+            Ok(Some(value)) => {
+                state
+                    .custom_gauges
+                    .lock()
+                    .unwrap()
+                    .insert(gauge.name.clone(), value);
+            }
+            Ok(None) => {
+                event!(
+                    Level::DEBUG,
+                    url,
+                    gauge = gauge.name,
+                    "No numeric value at {} in {} response",
+                    gauge.path,
+                    gauge.method
+                );
+            }
+            Err(e) => {
+                event!(
+                    Level::DEBUG,
+                    url,
+                    gauge = gauge.name,
+                    error = error_chain(&e),
+                    "Could not fetch custom gauge"
+                );
+            }
+        }
+    }
+}
+
+/// Establishes a WebSocket connection and builds a jsonrpsee client on top of it.
+///
+/// Connection phase timings are stored in `measured` whether or not the attempt
+/// succeeds, along with the certificate expiry of a `wss://` connection.
+///
+/// # Arguments
+///
+/// * `transport` - Transport used to establish the WebSocket connection
+/// * `config` - Monitor settings (timeouts)
+/// * `url` - WebSocket URL to connect to
+/// * `measured` - Values of the check, recorded once the whole check is done
+async fn connect(
+    transport: &Transport,
+    config: &MonitorConfig,
+    url: &str,
+    measured: &mut UrlCheck,
+) -> Result<WsClient> {
+    let result = time::timeout(
+        config.connection_timeout,
+        transport.connect(url, &mut measured.timings),
+    )
+    .await;
+
+    let connection =
+        result.unwrap_or_else(|_| bail!(ConnectError::Timeout(config.connection_timeout)))?;
+    event!(
+        Level::DEBUG,
+        "Connection established, negotiated subprotocol: {:?}",
+        connection.protocol
+    );
+    if let Some(expiry) = connection.cert_expiry {
+        event!(
+            Level::DEBUG,
+            "TLS certificate expires at Unix time {expiry}"
+        );
+        measured.cert_expiry = Some(expiry);
+    }
+
+    Ok(WsClientBuilder::new()
+        .request_timeout(config.request_timeout)
+        .build_with_transport(connection.sender, connection.receiver))
+}
+
+/// Opens a TCP connection to the URL's host and closes it again.
+///
+/// The DNS resolution time is stored in `timings` whether or not the attempt
+/// succeeds.
+///
+/// # Arguments
+///
+/// * `transport` - Transport used to open the connection
+/// * `config` - Monitor settings (timeouts)
+/// * `url` - WebSocket URL to connect to
+/// * `timings` - Connection phase timings of the check
+async fn connect_tcp(
+    transport: &Transport,
+    config: &MonitorConfig,
+    url: &str,
+    timings: &mut ConnectTimings,
+) -> Result<()> {
+    let result = time::timeout(
+        config.connection_timeout,
+        transport.connect_tcp(url, timings),
+    )
+    .await;
+
+    result.unwrap_or_else(|_| bail!(ConnectError::Timeout(config.connection_timeout)))
+}
+
+/// HTTP handler for the `/metrics` endpoint.
+///
+/// Returns Prometheus-formatted metrics showing the current success and failure counts,
+/// failures broken down by reason, and the up/down state of the monitored WebSocket
+/// endpoint.
+#[get("/metrics")]
+async fn metrics_handler(data: web::Data<AppState>) -> HttpResponse {
+    let mut snapshot = data.snapshot();
+    if data.delta_metrics {
+        snapshot.check_delta = Some(data.scrape_delta(&snapshot));
+    }
+    let mut response = prometheus_output(&data.ws_endpoint, &snapshot);
+    if let Some(status) = data.metrics_unhealthy_status.filter(|_| !snapshot.up) {
+        // For HTTP-check based alerting; the metrics are still returned
+        *response.status_mut() = status;
+    }
+    response
+}
+
+/// HTTP handler for the `/readyz` endpoint.
+///
+/// Returns 200 while the monitored endpoint is up and 503 while it is down or, if
+/// required, still syncing, with a configurable status during the startup grace period.
+#[get("/readyz")]
+async fn readiness_handler(data: web::Data<AppState>) -> HttpResponse {
+    let (status, message) = data.readiness();
+    HttpResponse::build(status).body(message)
+}
+
+/// JSON body of the `/status` endpoint.
+#[derive(Serialize)]
+struct StatusReport {
+    /// The WebSocket endpoint being monitored (redacted).
+    endpoint: String,
+    /// Whether the endpoint is currently considered up.
+    up: bool,
+    /// Whether checks are paused via the admin routes.
+    paused: bool,
+    /// Most recent failures, oldest first.
+    recent_errors: Vec<ErrorRecord>,
+}
+
+/// HTTP handler for the `/status` endpoint.
+///
+/// Returns the endpoint's up/down state and its most recent failures as JSON, for
+/// a quick look at recent history without going through the logs.
+#[get("/status")]
+async fn status_handler(data: web::Data<AppState>) -> HttpResponse {
+    HttpResponse::Ok().json(StatusReport {
+        endpoint: data.ws_endpoint.clone(),
+        up: data.health.is_up(),
+        paused: data.paused.load(Ordering::Relaxed),
+        recent_errors: data.recent_errors.snapshot(),
+    })
+}
+
+/// Query parameters of the admin routes.
+#[derive(Deserialize)]
+struct AdminQuery {
+    /// Endpoint the request applies to, all endpoints if not given.
+    endpoint: Option<String>,
+}
+
+/// HTTP handler for the `/admin/pause` endpoint.
+///
+/// Stops making checks until resumed; metrics keep their last-known values.
+#[post("/admin/pause")]
+async fn pause_handler(
+    data: web::Data<AppState>,
+    request: HttpRequest,
+    query: web::Query<AdminQuery>,
+) -> HttpResponse {
+    set_paused(&data, &request, &query, true)
+}
+
+/// HTTP handler for the `/admin/resume` endpoint.
+///
+/// Resumes making checks at the next scheduled tick.
+#[post("/admin/resume")]
+async fn resume_handler(
+    data: web::Data<AppState>,
+    request: HttpRequest,
+    query: web::Query<AdminQuery>,
+) -> HttpResponse {
+    set_paused(&data, &request, &query, false)
+}
+
+/// Pauses or resumes checks on behalf of an admin request.
+///
+/// # Arguments
+///
+/// * `data` - Shared application state holding the paused flag and admin token
+/// * `request` - Admin request, carrying the bearer token if one is required
+/// * `query` - Endpoint filter of the request
+/// * `paused` - Whether to pause or resume checks
+fn set_paused(
+    data: &AppState,
+    request: &HttpRequest,
+    query: &AdminQuery,
+    paused: bool,
+) -> HttpResponse {
+    if let Some(token) = &data.admin_token {
+        let authorization = request
+            .headers()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok());
+        if authorization != Some(&format!("Bearer {token}")) {
+            return HttpResponse::Unauthorized().body("unauthorized");
+        }
+    }
+    if query
+        .endpoint
+        .as_ref()
+        .is_some_and(|endpoint| *endpoint != data.ws_endpoint)
+    {
+        return HttpResponse::NotFound().body("unknown endpoint");
+    }
+
+    if data.paused.swap(paused, Ordering::Relaxed) != paused {
+        if paused {
+            event!(Level::INFO, "Checks paused via the admin API");
+        } else {
+            event!(Level::INFO, "Checks resumed via the admin API");
+        }
+    }
+    HttpResponse::Ok().body(if paused { "paused" } else { "resumed" })
+}
+
+/// Generates Prometheus-formatted metrics output.
+///
+/// Returns the encoded metrics as an HTTP response with the correct content type
+/// for Prometheus scraping.
+///
+/// # Arguments
+///
+/// * `endpoint` - The WebSocket endpoint being monitored (used as label)
+/// * `snapshot` - Current metric values
+fn prometheus_output(endpoint: &str, snapshot: &MetricsSnapshot) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(TextEncoder::new().format_type())
+        .body(encode_metrics(endpoint, snapshot))
+}
+
+/// Returns whether `name` clashes with a series of a built-in metric.
+fn is_builtin_metric(name: &str) -> bool {
+    BUILTIN_METRICS.contains(&name)
+        || BUILTIN_HISTOGRAMS.contains(&name)
+        || ["_bucket", "_sum", "_count"].iter().any(|suffix| {
+            name.strip_suffix(suffix)
+                .is_some_and(|base| BUILTIN_HISTOGRAMS.contains(&base))
+        })
+}
+
+/// Encodes all metrics in the Prometheus text exposition format.
+///
+/// Creates metrics with appropriate labels in a fresh registry populated from the
+/// snapshot. Shared by the `/metrics` endpoint and the textfile writer.
+///
+/// # Arguments
+///
+/// * `endpoint` - The WebSocket endpoint being monitored (used as label)
+/// * `snapshot` - Current metric values
+fn encode_metrics(endpoint: &str, snapshot: &MetricsSnapshot) -> Vec<u8> {
+    // Create counter metrics with endpoint label
+    let counter_opts =
+        Opts::new("check_count", "Counter for checks results").const_label("endpoint", endpoint);
+    let success_counter =
+        Counter::with_opts(counter_opts.clone().const_label("result", "SUCCESS")).unwrap();
+    let failure_counter =
+        Counter::with_opts(counter_opts.const_label("result", "TIMEOUT")).unwrap();
+
+    // Create and populate registry
+    let r = Registry::new();
+    r.register(Box::new(success_counter.clone())).unwrap();
+    r.register(Box::new(failure_counter.clone())).unwrap();
+
+    // Set counter values
+    success_counter.inc_by(snapshot.success as f64);
+    failure_counter.inc_by(snapshot.failure as f64);
+
+    // Create check count delta gauges (delta mode only)
+    if let Some((success, failure)) = snapshot.check_delta {
+        let delta_opts = Opts::new(
+            "check_count_delta",
+            "Number of checks since the previous scrape",
+        )
+        .const_label("endpoint", endpoint);
+        let success_gauge =
+            Gauge::with_opts(delta_opts.clone().const_label("result", "SUCCESS")).unwrap();
+        let failure_gauge = Gauge::with_opts(delta_opts.const_label("result", "TIMEOUT")).unwrap();
+        r.register(Box::new(success_gauge.clone())).unwrap();
+        r.register(Box::new(failure_gauge.clone())).unwrap();
+        success_gauge.set(success as f64);
+        failure_gauge.set(failure as f64);
+    }
+
+    // Create rolling error rate gauge (once a check completed within the window)
+    if let Some(error_rate) = snapshot.error_rate {
+        let error_rate_gauge = Gauge::with_opts(
+            Opts::new(
+                "check_error_rate",
+                "Share of failed checks within the rolling --error-rate-window",
+            )
+            .const_label("endpoint", endpoint),
+        )
+        .unwrap();
+        r.register(Box::new(error_rate_gauge.clone())).unwrap();
+        error_rate_gauge.set(error_rate);
+    }
+
+    // Create per-reason error counters
+    let error_opts = Opts::new("check_error_total", "Counter for failed checks by reason")
+        .const_label("endpoint", endpoint);
+    for &(reason, count) in &snapshot.errors {
+        let error_counter =
+            Counter::with_opts(error_opts.clone().const_label("reason", reason.as_str())).unwrap();
+        r.register(Box::new(error_counter.clone())).unwrap();
+        error_counter.inc_by(count as f64);
+    }
+
+    // Create monitored endpoint gauges (a single endpoint, disabled while paused)
+    let endpoints_gauge = Gauge::with_opts(Opts::new(
+        "monitored_endpoints",
+        "Number of configured endpoints",
+    ))
+    .unwrap();
+    let enabled_gauge = Gauge::with_opts(Opts::new(
+        "monitored_endpoints_enabled",
+        "Number of endpoints currently being checked",
+    ))
+    .unwrap();
+    r.register(Box::new(endpoints_gauge.clone())).unwrap();
+    r.register(Box::new(enabled_gauge.clone())).unwrap();
+    endpoints_gauge.set(1.0);
+    enabled_gauge.set(if snapshot.paused { 0.0 } else { 1.0 });
+
+    // Create fleet health gauges, across all enabled endpoints. The ratio is
+    // undefined without any, so it is left out while paused.
+    let (up, down) = match (snapshot.paused, snapshot.up) {
+        (true, _) => (0, 0),
+        (false, true) => (1, 0),
+        (false, false) => (0, 1),
+    };
+    let ratio = (up + down > 0).then(|| f64::from(up) / f64::from(up + down));
+    for (name, help, value) in [
+        (
+            "fleet_endpoints_up",
+            "Number of enabled endpoints currently up",
+            Some(f64::from(up)),
+        ),
+        (
+            "fleet_endpoints_down",
+            "Number of enabled endpoints currently down",
+            Some(f64::from(down)),
+        ),
+        (
+            "fleet_healthy_ratio",
+            "Ratio of enabled endpoints currently up",
+            ratio,
+        ),
+    ] {
+        if let Some(value) = value {
+            let gauge = Gauge::with_opts(Opts::new(name, help)).unwrap();
+            r.register(Box::new(gauge.clone())).unwrap();
+            gauge.set(value);
+        }
+    }
+
+    // Create log event counters (process-wide, not per endpoint)
+    let log_opts = Opts::new(
+        "log_events_total",
+        "Counter for log events emitted by the monitor at WARN and ERROR level",
+    );
+    for (level, count) in snapshot.log_events {
+        let log_counter = Counter::with_opts(log_opts.clone().const_label("level", level)).unwrap();
+        r.register(Box::new(log_counter.clone())).unwrap();
+        log_counter.inc_by(count as f64);
+    }
+
+    // Create panic counter
+    let panic_counter = Counter::with_opts(Opts::new(
+        "handler_panics_total",
+        "Counter for panics in the monitor, e.g. in HTTP handlers",
+    ))
+    .unwrap();
+    r.register(Box::new(panic_counter.clone())).unwrap();
+    panic_counter.inc_by(snapshot.panics as f64);
+
+    // Create paused gauge
+    let paused_gauge = Gauge::with_opts(
+        Opts::new(
+            "monitoring_paused",
+            "Whether checks are paused via the admin API (1) or running (0)",
+        )
+        .const_label("endpoint", endpoint),
+    )
+    .unwrap();
+    r.register(Box::new(paused_gauge.clone())).unwrap();
+    paused_gauge.set(if snapshot.paused { 1.0 } else { 0.0 });
+
+    // Create endpoint up/down gauge
+    let up_gauge = Gauge::with_opts(
+        Opts::new("endpoint_up", "Whether the endpoint is up (1) or down (0)")
+            .const_label("endpoint", endpoint),
+    )
+    .unwrap();
+    r.register(Box::new(up_gauge.clone())).unwrap();
+    up_gauge.set(if snapshot.up { 1.0 } else { 0.0 });
+
+    // Create consecutive failures gauge
+    let consecutive_failures_gauge = Gauge::with_opts(
+        Opts::new(
+            "consecutive_failures",
+            "Number of consecutive failed checks, reset to 0 by a success",
+        )
+        .const_label("endpoint", endpoint),
+    )
+    .unwrap();
+    r.register(Box::new(consecutive_failures_gauge.clone()))
+        .unwrap();
+    consecutive_failures_gauge.set(snapshot.consecutive_failures as f64);
+
+    // Create rate limiting counter
+    let rate_limited_counter = Counter::with_opts(
+        Opts::new(
+            "rate_limited_total",
+            "Counter for checks of a URL the gateway throttled, e.g. with HTTP status 429",
+        )
+        .const_label("endpoint", endpoint),
+    )
+    .unwrap();
+    r.register(Box::new(rate_limited_counter.clone())).unwrap();
+    rate_limited_counter.inc_by(snapshot.rate_limited as f64);
+
+    // Create honored retry-after counter
+    let retry_after_counter = Counter::with_opts(
+        Opts::new(
+            "retry_after_honored_total",
+            "Counter for checks delayed as asked by the server's Retry-After header",
+        )
+        .const_label("endpoint", endpoint),
+    )
+    .unwrap();
+    r.register(Box::new(retry_after_counter.clone())).unwrap();
+    retry_after_counter.inc_by(snapshot.retry_after_honored as f64);
+
+    // Create keepalive counter
+    let keepalive_counter = Counter::with_opts(
+        Opts::new(
+            "keepalive_calls_total",
+            "Counter for keepalive calls made on the idle persistent connection",
+        )
+        .const_label("endpoint", endpoint),
+    )
+    .unwrap();
+    r.register(Box::new(keepalive_counter.clone())).unwrap();
+    keepalive_counter.inc_by(snapshot.keepalives as f64);
+
+    // Create traffic counters
+    let (bytes_sent, bytes_received) = snapshot.traffic;
+    for (name, help, bytes) in [
+        (
+            "bytes_sent_total",
+            "Counter for JSON-RPC payload bytes sent to the node over WebSocket",
+            bytes_sent,
+        ),
+        (
+            "bytes_received_total",
+            "Counter for JSON-RPC payload bytes received from the node over WebSocket",
+            bytes_received,
+        ),
+    ] {
+        let bytes_counter =
+            Counter::with_opts(Opts::new(name, help).const_label("endpoint", endpoint)).unwrap();
+        r.register(Box::new(bytes_counter.clone())).unwrap();
+        bytes_counter.inc_by(bytes as f64);
+    }
+
+    // Create three-state endpoint gauge and degraded check counter (if enabled)
+    if let Some((degraded, degraded_checks)) = snapshot.degraded {
+        let state_gauge = Gauge::with_opts(
+            Opts::new(
+                "endpoint_state",
+                "State of the endpoint: down (0), up (1) or degraded (2)",
+            )
+            .const_label("endpoint", endpoint),
+        )
+        .unwrap();
+        r.register(Box::new(state_gauge.clone())).unwrap();
+        state_gauge.set(match (snapshot.up, degraded) {
+            (false, _) => 0.0,
+            (true, false) => 1.0,
+            (true, true) => 2.0,
+        });
+
+        let degraded_counter = Counter::with_opts(
+            Opts::new(
+                "degraded_checks_total",
+                "Counter for successful checks slower than the degraded latency threshold",
+            )
+            .const_label("endpoint", endpoint),
+        )
+        .unwrap();
+        r.register(Box::new(degraded_counter.clone())).unwrap();
+        degraded_counter.inc_by(degraded_checks as f64);
+    }
+
+    // Create configured timeout gauges
+    let connection_timeout_gauge = Gauge::with_opts(
+        Opts::new(
+            "configured_connection_timeout_seconds",
+            "Configured timeout for establishing the connection in seconds",
+        )
+        .const_label("endpoint", endpoint),
+    )
+    .unwrap();
+    let request_timeout_gauge = Gauge::with_opts(
+        Opts::new(
+            "configured_request_timeout_seconds",
+            "Configured timeout for RPC requests in seconds",
+        )
+        .const_label("endpoint", endpoint),
+    )
+    .unwrap();
+    r.register(Box::new(connection_timeout_gauge.clone()))
+        .unwrap();
+    r.register(Box::new(request_timeout_gauge.clone())).unwrap();
+    connection_timeout_gauge.set(snapshot.connection_timeout.as_secs_f64());
+    request_timeout_gauge.set(snapshot.request_timeout.as_secs_f64());
+
+    // Create connection lifetime metrics (persistent mode only)
+    if let Some(connection) = &snapshot.connection {
+        let established_counter = Counter::with_opts(
+            Opts::new(
+                "connection_established_total",
+                "Counter for successfully established connections",
+            )
+            .const_label("endpoint", endpoint),
+        )
+        .unwrap();
+        let age_gauge = Gauge::with_opts(
+            Opts::new(
+                "connection_age_seconds",
+                "Age of the current live connection in seconds",
+            )
+            .const_label("endpoint", endpoint),
+        )
+        .unwrap();
+        let reconnect_opts = Opts::new(
+            "connection_reconnect_total",
+            "Counter for re-established connections by reason",
+        )
+        .const_label("endpoint", endpoint);
+        let expired_counter =
+            Counter::with_opts(reconnect_opts.clone().const_label("reason", "max_age")).unwrap();
+        let lost_counter =
+            Counter::with_opts(reconnect_opts.const_label("reason", "error")).unwrap();
+        r.register(Box::new(established_counter.clone())).unwrap();
+        r.register(Box::new(expired_counter.clone())).unwrap();
+        r.register(Box::new(lost_counter.clone())).unwrap();
+        r.register(Box::new(age_gauge.clone())).unwrap();
+        established_counter.inc_by(connection.established_total as f64);
+        expired_counter.inc_by(connection.expired_total as f64);
+        lost_counter.inc_by(connection.lost_total as f64);
+        age_gauge.set(connection.age.map_or(0.0, |age| age.as_secs_f64()));
+    }
+
+    // Create per-URL result metrics (only with fallback URLs)
+    if let Some(urls) = &snapshot.urls {
+        let url_opts = Opts::new("url_check_count", "Counter for check attempts per URL")
+            .const_label("endpoint", endpoint);
+        let used_opts = Opts::new(
+            "url_used",
+            "Whether the URL served the last successful check (1) or not (0)",
+        )
+        .const_label("endpoint", endpoint);
+        for url in urls {
+            let url_opts = url_opts.clone().const_label("url", &url.url);
+            let success_counter =
+                Counter::with_opts(url_opts.clone().const_label("result", "SUCCESS")).unwrap();
+            let failure_counter =
+                Counter::with_opts(url_opts.const_label("result", "TIMEOUT")).unwrap();
+            let used_gauge =
+                Gauge::with_opts(used_opts.clone().const_label("url", &url.url)).unwrap();
+            r.register(Box::new(success_counter.clone())).unwrap();
+            r.register(Box::new(failure_counter.clone())).unwrap();
+            r.register(Box::new(used_gauge.clone())).unwrap();
+            success_counter.inc_by(url.success as f64);
+            failure_counter.inc_by(url.failure as f64);
+            used_gauge.set(if url.used { 1.0 } else { 0.0 });
+        }
+    }
+
+    // Create unexpected response change counters (only with change detection)
+    for (field, count) in &snapshot.response_changes {
+        let change_counter = Counter::with_opts(
+            Opts::new(
+                "unexpected_change_total",
+                "Counter for unexpected changes of chain identity fields between checks",
+            )
+            .const_label("endpoint", endpoint)
+            .const_label("field", *field),
+        )
+        .unwrap();
+        r.register(Box::new(change_counter.clone())).unwrap();
+        change_counter.inc_by(*count as f64);
+    }
+
+    // Create deserialization failure counters (only for types that failed)
+    for (target, count) in &snapshot.deserialization_errors {
+        let deserialization_counter = Counter::with_opts(
+            Opts::new(
+                "deserialization_error_total",
+                "Counter for responses that did not match the expected type",
+            )
+            .const_label("endpoint", endpoint)
+            .const_label("target", *target),
+        )
+        .unwrap();
+        r.register(Box::new(deserialization_counter.clone()))
+            .unwrap();
+        deserialization_counter.inc_by(*count as f64);
+    }
+
+    // Create status RPC sub-call counters (only for calls made)
+    for ((call, result), count) in &snapshot.rpc_calls {
+        let call_counter = Counter::with_opts(
+            Opts::new("rpc_call_count", "Counter for status RPC sub-call results")
+                .const_label("endpoint", endpoint)
+                .const_label("call", *call)
+                .const_label("result", *result),
+        )
+        .unwrap();
+        r.register(Box::new(call_counter.clone())).unwrap();
+        call_counter.inc_by(*count as f64);
+    }
+
+    // Create check scheduling delay gauge (after the first monitor check)
+    if let Some(delay) = snapshot.schedule_delay {
+        let delay_gauge = Gauge::with_opts(
+            Opts::new(
+                "check_schedule_delay_seconds",
+                "Delay between the scheduled and actual start of the last check in seconds",
+            )
+            .const_label("endpoint", endpoint),
+        )
+        .unwrap();
+        r.register(Box::new(delay_gauge.clone())).unwrap();
+        delay_gauge.set(delay.as_secs_f64());
+
+        let pending_gauge = Gauge::with_opts(
+            Opts::new(
+                "scheduler_pending_checks",
+                "Scheduled checks that were overdue when the last check started",
+            )
+            .const_label("endpoint", endpoint),
+        )
+        .unwrap();
+        r.register(Box::new(pending_gauge.clone())).unwrap();
+        pending_gauge.set(snapshot.pending_checks as f64);
+    }
+
+    // Create check mode info metric
+    let mode_gauge = Gauge::with_opts(
+        Opts::new("check_mode_info", "Check mode, as the mode label")
+            .const_label("endpoint", endpoint)
+            .const_label("mode", snapshot.check_mode.as_str()),
+    )
+    .unwrap();
+    r.register(Box::new(mode_gauge.clone())).unwrap();
+    mode_gauge.set(1.0);
+
+    // Create endpoint info metric (once the node attributes are discovered)
+    if let Some(info) = &snapshot.node_info {
+        let info_gauge = Gauge::with_opts(
+            Opts::new(
+                "endpoint_info",
+                "Descriptive attributes of the endpoint, always 1",
+            )
+            .const_label("endpoint", endpoint)
+            .const_label("chain", info.chain.as_deref().unwrap_or("unknown"))
+            .const_label("version", info.version.as_deref().unwrap_or("unknown"))
+            .const_label("region", snapshot.region.as_deref().unwrap_or_default()),
+        )
+        .unwrap();
+        r.register(Box::new(info_gauge.clone())).unwrap();
+        info_gauge.set(1.0);
+    }
+
+    // Create archive node gauge (once detected)
+    if let Some(archive) = snapshot.archive {
+        let archive_gauge = Gauge::with_opts(
+            Opts::new(
+                "endpoint_is_archive",
+                "Whether the endpoint keeps the state of all blocks (1) or prunes it (0)",
+            )
+            .const_label("endpoint", endpoint),
+        )
+        .unwrap();
+        r.register(Box::new(archive_gauge.clone())).unwrap();
+        archive_gauge.set(if archive { 1.0 } else { 0.0 });
+    }
+
+    // Create HTTP fallback gauge (if allowed)
+    if let Some(http_fallback) = snapshot.http_fallback {
+        let fallback_gauge = Gauge::with_opts(
+            Opts::new(
+                "endpoint_http_fallback",
+                "Whether the last check fell back to HTTP JSON-RPC (1) or used WebSocket (0)",
+            )
+            .const_label("endpoint", endpoint),
+        )
+        .unwrap();
+        r.register(Box::new(fallback_gauge.clone())).unwrap();
+        fallback_gauge.set(if http_fallback { 1.0 } else { 0.0 });
+    }
+
+    // Create time to first success gauge (frozen after the first successful check)
+    if let Some(first_success) = snapshot.first_success {
+        let first_success_gauge = Gauge::with_opts(
+            Opts::new(
+                "time_to_first_success_seconds",
+                "Time from process start to the first successful check in seconds",
+            )
+            .const_label("endpoint", endpoint),
+        )
+        .unwrap();
+        r.register(Box::new(first_success_gauge.clone())).unwrap();
+        first_success_gauge.set(first_success.as_secs_f64());
+    }
+
+    // Create TLS certificate expiry gauge (after the first wss:// connection)
+    if let Some(expiry) = snapshot.cert_expiry {
+        let expiry_gauge = Gauge::with_opts(
+            Opts::new(
+                "endpoint_tls_cert_expiry_timestamp_seconds",
+                "Earliest notAfter of the endpoint's TLS certificates as a Unix timestamp",
+            )
+            .const_label("endpoint", endpoint),
+        )
+        .unwrap();
+        r.register(Box::new(expiry_gauge.clone())).unwrap();
+        expiry_gauge.set(expiry as f64);
+    }
+
+    // Register connection phase histograms
+    r.register(Box::new(snapshot.dns_resolution.clone()))
+        .unwrap();
+    r.register(Box::new(snapshot.ws_upgrade.clone())).unwrap();
+
+    // Register check duration histograms
+    r.register(Box::new(snapshot.check_latency.clone()))
+        .unwrap();
+    r.register(Box::new(snapshot.check_total_duration.clone()))
+        .unwrap();
+
+    // Create block and sync gauges (once reported by a successful check)
+    if let Some(chain) = &snapshot.chain {
+        for (name, help, value) in [
+            (
+                "finalized_block_number",
+                "Number of the finalized block reported by the endpoint",
+                chain.finalized_block_number,
+            ),
+            (
+                "sync_lag_blocks",
+                "Number of blocks the endpoint is behind the highest known block",
+                chain.sync_lag_blocks,
+            ),
+            (
+                "peer_count",
+                "Number of peers the endpoint is connected to",
+                chain.peers,
+            ),
+            (
+                "head_finality_gap_blocks",
+                "Number of blocks the best head is ahead of the finalized head",
+                chain.finality_gap_blocks,
+            ),
+            (
+                "runtime_spec_version",
+                "Spec version of the runtime at the endpoint's best head",
+                chain.spec_version,
+            ),
+        ] {
+            let Some(value) = value else {
+                continue;
+            };
+            let gauge =
+                Gauge::with_opts(Opts::new(name, help).const_label("endpoint", endpoint)).unwrap();
+            r.register(Box::new(gauge.clone())).unwrap();
+            gauge.set(value as f64);
+        }
+    }
+
+    // Create block production rate gauge (once two checks reported a block number)
+    if let Some(blocks_per_second) = snapshot.blocks_per_second {
+        let rate_gauge = Gauge::with_opts(
+            Opts::new(
+                "blocks_per_second",
+                "Finalized blocks per second between the last two checks reporting a block number",
+            )
+            .const_label("endpoint", endpoint),
+        )
+        .unwrap();
+        r.register(Box::new(rate_gauge.clone())).unwrap();
+        rate_gauge.set(blocks_per_second);
+    }
+
+    // Create finalized block age gauge (once the block timestamp was fetched)
+    let block_timestamp = snapshot
+        .chain
+        .as_ref()
+        .and_then(|chain| chain.block_timestamp_ms);
+    if let Some(timestamp) = block_timestamp {
+        // Clocks of node and monitor may differ, so a block from the future is zero old
+        let age = (UNIX_EPOCH + Duration::from_millis(timestamp))
+            .elapsed()
+            .unwrap_or_default();
+        let age_gauge = Gauge::with_opts(
+            Opts::new(
+                "finalized_block_age_seconds",
+                "Wall-clock time since the timestamp of the finalized block",
+            )
+            .const_label("endpoint", endpoint),
+        )
+        .unwrap();
+        r.register(Box::new(age_gauge.clone())).unwrap();
+        age_gauge.set(age.as_secs_f64());
+    }
+
+    // Create custom gauges (once they have a value). Names of built-in metrics are
+    // rejected when parsing the arguments, so only repeated names are skipped.
+    for (name, value) in &snapshot.custom_gauges {
+        let custom_gauge = Gauge::with_opts(
+            Opts::new(name, "Value of an RPC response field set by --custom-gauge")
+                .const_label("endpoint", endpoint),
+        )
+        .unwrap();
+        if r.register(Box::new(custom_gauge.clone())).is_ok() {
+            custom_gauge.set(*value);
+        }
+    }
+
+    // Encode metrics to Prometheus text format
+    let mut buffer = vec![];
+    let encoder = TextEncoder::new();
+    let mut metric_families = r.gather();
+    if !snapshot.metric_prefix.is_empty() {
+        for family in &mut metric_families {
+            let name = format!("{}{}", snapshot.metric_prefix, family.name());
+            family.set_name(name);
+        }
+    }
+
+    // Create blackbox_exporter compatible gauges, unprefixed and without labels as
+    // dashboards built for blackbox_exporter expect them
+    if let Some(probe) = snapshot.last_probe {
+        let blackbox = Registry::new();
+        for (name, help, value) in [
+            (
+                "probe_success",
+                "Whether the last check succeeded",
+                f64::from(u8::from(probe.success)),
+            ),
+            (
+                "probe_duration_seconds",
+                "Total duration of the last check",
+                probe.duration.as_secs_f64(),
+            ),
+            (
+                "probe_dns_lookup_time_seconds",
+                "DNS resolution time of the last check",
+                probe.dns_lookup.as_secs_f64(),
+            ),
+        ] {
+            let gauge = Gauge::with_opts(Opts::new(name, help)).unwrap();
+            blackbox.register(Box::new(gauge.clone())).unwrap();
+            gauge.set(value);
+        }
+        metric_families.extend(blackbox.gather());
+    }
+    encoder.encode(&metric_families, &mut buffer).unwrap();
+
+    buffer
+}
+
+/// Creates a histogram labelled with the monitored endpoint.
+///
+/// # Arguments
+///
+/// * `name` - Metric name
+/// * `help` - Metric help text
+/// * `endpoint` - The WebSocket endpoint being monitored (used as label)
+/// * `buckets` - Upper bounds of the histogram buckets, in seconds
+fn endpoint_histogram(
+    name: &str,
+    help: &str,
+    endpoint: &str,
+    buckets: &[f64],
+) -> Result<Histogram> {
+    Ok(Histogram::with_opts(
+        HistogramOpts::new(name, help)
+            .const_label("endpoint", endpoint)
+            .buckets(buckets.to_vec()),
+    )?)
+}
+
+/// Creates the histogram of RPC round-trip times of successful checks.
+///
+/// The `warm` label tells checks reusing a connection from checks on a new one,
+/// whose first requests are slower. Both series are created upfront.
+fn check_latency_histogram(endpoint: &str, buckets: &[f64]) -> Result<HistogramVec> {
+    let histogram = HistogramVec::new(
+        HistogramOpts::new(
+            "check_latency_seconds",
+            "Histogram of RPC round-trip times of successful checks",
+        )
+        .const_label("endpoint", endpoint)
+        .buckets(buckets.to_vec()),
+        &["warm"],
+    )?;
+    for warm in ["true", "false"] {
+        histogram.get_metric_with_label_values(&[warm])?;
+    }
+    Ok(histogram)
+}
+
+/// Creates the histogram of total check durations.
+fn check_total_duration_histogram(endpoint: &str, buckets: &[f64]) -> Result<Histogram> {
+    endpoint_histogram(
+        "check_total_duration_seconds",
+        "Histogram of total check durations, including connection establishment",
+        endpoint,
+        buckets,
+    )
+}
+
+/// Resolves the node URL and drops duplicate fallback URLs, logging a warning
+/// for each.
+fn resolve_node_urls(args: &mut CheckArgs) -> Result<()> {
+    args.resolve_node_url()?;
+    for url in args.dedup_urls() {
+        event!(
+            Level::WARN,
+            "Ignoring duplicate node URL {}",
+            redact_url(&url)
+        );
+    }
+
+    Ok(())
+}
+
+/// Validates the primary and all fallback node URLs.
+fn check_node_urls(args: &CheckArgs) -> Result<()> {
+    check_node_url(&args.node_url)?;
+    for url in &args.fallback_url {
+        check_node_url(url)?;
+    }
+
+    Ok(())
+}
+
+/// Reads the bearer token required by the admin routes.
+///
+/// Fails if the file cannot be read or is empty.
+fn read_admin_token(path: &Path) -> Result<String> {
+    let token = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read admin token file '{}'", path.display()))?;
+    let token = token.trim();
+    if token.is_empty() {
+        bail!("Admin token file '{}' is empty", path.display());
+    }
+
+    Ok(token.to_owned())
+}
+
+/// Validates and normalizes the HTTP base path.
+///
+/// # Arguments
+///
+/// * `base_path` - The path prefix to validate
+///
+/// # Returns
+///
+/// * `Ok(String)` with the trailing slash removed, or an empty string for no prefix
+/// * `Err` if the path does not start with `/`
+fn normalize_base_path(base_path: &str) -> Result<String> {
+    let base_path = base_path.trim_end_matches('/');
+    if !base_path.is_empty() && !base_path.starts_with('/') {
+        bail!("Base path '{}' must start with '/'", base_path);
+    }
+
+    Ok(base_path.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AppState, BUILTIN_HISTOGRAMS, BUILTIN_METRICS, ConnectionSnapshot, MonitorConfig, Probe,
+        build_transport, connection_monitor, encode_metrics, perform_check,
+    };
+    use crate::chain::{ChainStatus, NodeInfo};
+    use crate::cli::{Cli, Command};
+    use crate::error_reason::{CheckError, ErrorReason};
+    use crate::health::HealthTracker;
+    use crate::transport::Transport;
+    use crate::url_stats::UrlSnapshot;
+    use clap::Parser;
+    use serde_json::{Value, json};
+    use soketto::handshake::{Server, server::Response};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::time::{self, Instant};
+    use tokio_util::compat::TokioAsyncReadCompatExt;
+    use tokio_util::sync::CancellationToken;
+
+    /// Serves JSON-RPC over WebSocket, answering each method with the result
+    /// returned by `respond`, or an error if it returns `None`.
+    ///
+    /// Returns the URL of the server and the number of connections it accepted.
+    async fn serve_rpc(respond: fn(&str) -> Option<Value>) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = connections.clone();
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                accepted.fetch_add(1, Ordering::Relaxed);
+                tokio::spawn(async move {
+                    let mut server = Server::new(socket.compat());
+                    let key = server.receive_request().await.unwrap().key();
+                    let accept = Response::Accept {
+                        key,
+                        protocol: None,
+                    };
+                    server.send_response(&accept).await.unwrap();
+                    let (mut sender, mut receiver) = server.into_builder().finish();
+                    let mut message = Vec::new();
+                    while receiver.receive_data(&mut message).await.is_ok() {
+                        let request: Value = serde_json::from_slice(&message).unwrap();
+                        message.clear();
+                        let response = match respond(request["method"].as_str().unwrap()) {
+                            Some(result) => {
+                                json!({"jsonrpc": "2.0", "id": request["id"], "result": result})
+                            }
+                            None => json!({
+                                "jsonrpc": "2.0",
+                                "id": request["id"],
+                                "error": {"code": -32601, "message": "Method not found"},
+                            }),
+                        };
+                        sender.send_text(response.to_string()).await.unwrap();
+                        sender.flush().await.unwrap();
+                    }
+                });
+            }
+        });
+        (url, connections)
+    }
+
+    /// Serves a stub endpoint answering every request with `response` after `hold`,
+    /// then closing the connection.
+    ///
+    /// Returns its URL and when each connection was accepted, by tokio's clock.
+    async fn serve_stub(
+        response: &'static str,
+        hold: Duration,
+    ) -> (String, Arc<Mutex<Vec<Instant>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let accepted = Arc::new(Mutex::new(Vec::new()));
+        let times = accepted.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                times.lock().unwrap().push(Instant::now());
+                tokio::spawn(async move {
+                    let mut request = [0; 4096];
+                    let _ = stream.read(&mut request).await;
+                    time::sleep(hold).await;
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        (url, accepted)
+    }
+
+    /// Runs the monitor loop for `duration` of tokio's paused clock, checking every
+    /// ten seconds, and returns the offsets of the accepted connections.
+    ///
+    /// The clock is advanced a second at a time, letting the stub's IO complete in
+    /// between, so checks never time out just because nothing else was pending.
+    async fn run_monitor_for(
+        url: &str,
+        args: &[&str],
+        accepted: &Mutex<Vec<Instant>>,
+        duration: Duration,
+    ) -> (AppState, Vec<u64>) {
+        let (config, state, transport) = monitor(&[&[url], args].concat());
+        let started = Instant::now();
+        // A running blocking task keeps the paused clock from advancing on its own
+        let (release, parked) = std::sync::mpsc::channel::<()>();
+        let guard = tokio::task::spawn_blocking(move || parked.recv());
+        let shutdown = CancellationToken::new();
+        let monitor = tokio::spawn(connection_monitor(
+            config,
+            Duration::from_secs(10),
+            transport,
+            state.clone(),
+            None,
+            None,
+            shutdown.clone(),
+        ));
+        for _ in 0..duration.as_secs() {
+            settle().await;
+            time::advance(Duration::from_secs(1)).await;
+        }
+        settle().await;
+        // A check may still be running, so the loop is stopped without waiting for it
+        monitor.abort();
+        let _ = monitor.await;
+        drop(release);
+        let _ = guard.await.unwrap();
+
+        let offsets = accepted
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|at| (*at - started).as_secs())
+            .collect();
+        (state, offsets)
+    }
+
+    /// Waits briefly in real time, so pending IO on the loopback interface completes.
+    async fn settle() {
+        tokio::task::spawn_blocking(|| std::thread::sleep(Duration::from_millis(5)))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn backs_off_while_rate_limited() {
+        let (url, accepted) = serve_stub(
+            "HTTP/1.1 429 Too Many Requests\r\nContent-Length: 0\r\n\r\n",
+            Duration::ZERO,
+        )
+        .await;
+        let (state, offsets) = run_monitor_for(
+            &url,
+            &["--rate-limit-backoff"],
+            &accepted,
+            Duration::from_secs(150),
+        )
+        .await;
+
+        // The interval doubles after every throttled check
+        assert_eq!(offsets, [0, 20, 60, 140]);
+        assert_eq!(state.rate_limited.load(Ordering::Relaxed), 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_as_long_as_retry_after_asks() {
+        let (url, accepted) = serve_stub(
+            "HTTP/1.1 429 Too Many Requests\r\nRetry-After: 25\r\nContent-Length: 0\r\n\r\n",
+            Duration::ZERO,
+        )
+        .await;
+        let (state, offsets) = run_monitor_for(
+            &url,
+            &["--honor-retry-after"],
+            &accepted,
+            Duration::from_secs(60),
+        )
+        .await;
+
+        assert_eq!(offsets, [0, 25, 50]);
+        assert_eq!(state.retry_after_honored.load(Ordering::Relaxed), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reports_overrunning_checks() {
+        // Every check takes 25 seconds, more than two intervals
+        let (url, accepted) = serve_stub("", Duration::from_secs(25)).await;
+        let (state, offsets) = run_monitor_for(
+            &url,
+            &["--monitor-connection-timeout", "60"],
+            &accepted,
+            Duration::from_secs(30),
+        )
+        .await;
+
+        // The tick due at 10s fired once the first check ended at 25s, with the
+        // tick due at 20s still pending
+        assert_eq!(offsets, [0, 25]);
+        assert_eq!(
+            *state.schedule_delay.lock().unwrap(),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(state.pending_checks.load(Ordering::Relaxed), 1);
+    }
+
+    /// Builds the monitor's configuration, state and transport from its arguments.
+    fn monitor(args: &[&str]) -> (MonitorConfig, AppState, Transport) {
+        let args = [&["wsmon", "monitor"], args].concat();
+        let Command::Monitor(mut args) = Cli::try_parse_from(args).unwrap().command else {
+            panic!("expected the monitor subcommand");
+        };
+        args.check.resolve_node_url().unwrap();
+        let state = AppState::new(
+            &args.check,
+            HealthTracker::new(args.failure_threshold, args.success_threshold),
+            args.persistent,
+            Duration::ZERO,
+            false,
+            Arc::default(),
+            args.recent_errors,
+        )
+        .unwrap();
+        let transport = build_transport(&args.check).unwrap();
+        (MonitorConfig::monitor(&args), state, transport)
+    }
+
+    #[tokio::test]
+    async fn keeps_connection_after_low_peers() {
+        let (url, connections) = serve_rpc(|method| match method {
+            "eth_blockNumber" => Some(json!("0x10")),
+            "eth_syncing" => Some(json!(false)),
+            "net_peerCount" => Some(json!("0x1")),
+            _ => None,
+        })
+        .await;
+        let (config, state, transport) = monitor(&[
+            &url,
+            "--persistent",
+            "--chain-type",
+            "ethereum",
+            "--min-peers",
+            "5",
+        ]);
+
+        let mut active = None;
+        for _ in 0..2 {
+            let result = perform_check(&transport, &config, &state, &mut active).await;
+            let error = result.unwrap_err();
+            assert_eq!(error.reason, ErrorReason::LowPeers);
+            assert!(error.message.starts_with("Too few peers"));
+            assert!(active.is_some());
+        }
+        assert_eq!(connections.load(Ordering::Relaxed), 1);
+        assert_eq!(state.connections.lost_total(), 0);
+    }
+
+    #[test]
+    fn scrapes_consistent_snapshots_during_checks() {
+        const CHECKS: u64 = 20_000;
+        let (_, state, _) = monitor(&["ws://127.0.0.1:9944"]);
+
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                for i in 0..CHECKS {
+                    let result = match i % 3 {
+                        0 => Ok(()),
+                        1 => Err(CheckError::new(
+                            ErrorReason::Timeout,
+                            "Request timeout".to_owned(),
+                        )),
+                        _ => Err(CheckError::new(
+                            ErrorReason::Refused,
+                            "Connection refused".to_owned(),
+                        )),
+                    };
+                    state.record_check(Vec::new(), &result, Duration::from_millis(1));
+                }
+            });
+            for _ in 0..2 {
+                scope.spawn(|| {
+                    loop {
+                        let snapshot = state.snapshot();
+                        let durations = snapshot.check_total_duration.families[0].get_metric()[0]
+                            .get_histogram()
+                            .get_sample_count();
+                        assert_eq!(snapshot.success + snapshot.failure, durations as usize);
+                        let errors: usize = snapshot.errors.iter().map(|(_, count)| count).sum();
+                        assert_eq!(errors, snapshot.failure);
+                        if durations == CHECKS {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+    }
+
+    #[tokio::test]
+    async fn records_connection_timings_with_the_check() {
+        let (url, _) = serve_rpc(|method| match method {
+            "eth_blockNumber" => Some(json!("0x10")),
+            "eth_syncing" => Some(json!(false)),
+            "net_peerCount" => Some(json!("0x5")),
+            _ => None,
+        })
+        .await;
+        let (config, state, transport) = monitor(&[&url, "--chain-type", "ethereum"]);
+
+        perform_check(&transport, &config, &state, &mut None)
+            .await
+            .unwrap();
+        let metrics =
+            String::from_utf8(encode_metrics(&state.ws_endpoint, &state.snapshot())).unwrap();
+        let count = |name: &str| {
+            metrics
+                .lines()
+                .find(|line| line.starts_with(name))
+                .and_then(|line| line.rsplit(' ').next())
+                .map(str::to_owned)
+        };
+        assert_eq!(count("ws_upgrade_seconds_count").as_deref(), Some("1"));
+        // IP literals are not resolved
+        assert_eq!(count("dns_resolution_seconds_count").as_deref(), Some("0"));
+    }
+
+    #[tokio::test]
+    async fn keeps_connection_after_rpc_error() {
+        let (url, connections) = serve_rpc(|method| match method {
+            "eth_blockNumber" => Some(json!("0x10")),
+            _ => None,
+        })
+        .await;
+        let (config, state, transport) =
+            monitor(&[&url, "--persistent", "--chain-type", "ethereum"]);
+
+        let mut active = None;
+        for _ in 0..2 {
+            let result = perform_check(&transport, &config, &state, &mut active).await;
+            assert!(result.unwrap_err().message.contains("Method not found"));
+            assert!(active.is_some());
+        }
+        assert_eq!(connections.load(Ordering::Relaxed), 1);
+        assert_eq!(state.connections.lost_total(), 0);
+    }
+
+    #[test]
+    fn lists_all_builtin_metrics() {
+        let (_, state, _) = monitor(&["ws://127.0.0.1:9944", "--persistent"]);
+        // Every optional family present
+        let mut snapshot = state.snapshot();
+        snapshot.check_delta = Some((1, 1));
+        snapshot.degraded = Some((false, 1));
+        snapshot.blocks_per_second = Some(1.0);
+        snapshot.error_rate = Some(0.5);
+        snapshot.connection = Some(ConnectionSnapshot {
+            established_total: 1,
+            expired_total: 0,
+            lost_total: 0,
+            age: Some(Duration::from_secs(1)),
+        });
+        snapshot.urls = Some(vec![UrlSnapshot {
+            url: "ws://127.0.0.1:9944".to_owned(),
+            success: 1,
+            failure: 0,
+            used: true,
+        }]);
+        snapshot.chain = Some(ChainStatus {
+            finalized_hash: Some("0x01".to_owned()),
+            finalized_block_number: Some(1),
+            sync_lag_blocks: Some(0),
+            peers: Some(1),
+            finality_gap_blocks: Some(0),
+            spec_version: Some(1),
+            block_timestamp_ms: Some(0),
+        });
+        snapshot.cert_expiry = Some(0);
+        snapshot.last_probe = Some(Probe {
+            success: true,
+            duration: Duration::from_secs(1),
+            dns_lookup: Duration::ZERO,
+        });
+        snapshot.node_info = Some(NodeInfo {
+            chain: None,
+            version: None,
+        });
+        snapshot.archive = Some(true);
+        snapshot.http_fallback = Some(false);
+        snapshot.schedule_delay = Some(Duration::ZERO);
+        snapshot.first_success = Some(Duration::ZERO);
+        snapshot.response_changes = vec![("genesis", 1)];
+        snapshot.deserialization_errors = vec![("Header", 1)];
+        snapshot.rpc_calls = vec![(("health", "SUCCESS"), 1)];
+
+        let metrics = String::from_utf8(encode_metrics(&state.ws_endpoint, &snapshot)).unwrap();
+        for family in metrics
+            .lines()
+            .filter_map(|line| line.strip_prefix("# TYPE "))
+        {
+            let listed = match family.split_once(' ') {
+                Some((name, "histogram")) => BUILTIN_HISTOGRAMS.contains(&name),
+                Some((name, _)) => BUILTIN_METRICS.contains(&name),
+                None => false,
+            };
+            assert!(listed, "{family}");
+        }
+    }
+
+    #[tokio::test]
+    async fn scrapes_no_enabled_endpoints_while_paused() {
+        let (_, state, _) = monitor(&["ws://127.0.0.1:9944"]);
+        let scrape = |state: &AppState| {
+            String::from_utf8(encode_metrics(&state.ws_endpoint, &state.snapshot())).unwrap()
+        };
+        let metrics = scrape(&state);
+        for line in [
+            "monitored_endpoints_enabled 1",
+            "fleet_endpoints_down 1",
+            "fleet_healthy_ratio 0",
+        ] {
+            assert!(metrics.lines().any(|l| l == line), "{line}");
+        }
+
+        state.paused.store(true, Ordering::Relaxed);
+        let metrics = scrape(&state);
+        for line in [
+            "monitored_endpoints 1",
+            "monitored_endpoints_enabled 0",
+            "fleet_endpoints_up 0",
+            "fleet_endpoints_down 0",
+        ] {
+            assert!(metrics.lines().any(|l| l == line), "{line}");
+        }
+        assert!(!metrics.contains("fleet_healthy_ratio"));
+    }
 }
@@ -0,0 +1,203 @@
+//! Validation and redaction of node URLs.
+
+use anyhow::{Result, bail};
+use url::{ParseError, Url};
+
+/// Validates that the provided node URL is a valid WebSocket URL.
+///
+/// # Arguments
+///
+/// * `node_url` - The URL string to validate
+///
+/// # Returns
+///
+/// * `Ok(())` if the URL is valid
+/// * `Err` if the URL cannot be parsed or uses an unsupported scheme
+pub fn check_node_url(node_url: &str) -> Result<()> {
+    let parsed = match Url::parse(node_url) {
+        Ok(parsed) => parsed,
+        Err(ParseError::InvalidPort | ParseError::EmptyHost) if is_unbracketed_ipv6(node_url) => {
+            bail!(
+                "Invalid node URL '{}': IPv6 addresses must be enclosed in brackets, e.g. ws://[::1]:9944",
+                redact_url(node_url)
+            )
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let scheme = parsed.scheme();
+    match scheme {
+        "ws" | "wss" => {}
+        _ => {
+            bail!("Unsupported URL scheme '{}'", scheme);
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns whether the URL's authority looks like an IPv6 address without brackets.
+fn is_unbracketed_ipv6(url: &str) -> bool {
+    let authority = url
+        .split_once("://")
+        .map_or(url, |(_, rest)| rest)
+        .split(['/', '?', '#'])
+        .next()
+        .unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    !host.starts_with('[') && host.matches(':').count() > 1
+}
+
+/// Replaces the username, password and query parameter values in a URL with a
+/// placeholder.
+///
+/// API keys embedded in the path (e.g. `/v3/<key>`) are kept, as they cannot be
+/// told apart from the rest of the path. URLs that cannot be parsed are redacted
+/// textually. Returns the input unchanged if it has none of these parts.
+pub fn redact_url(url: &str) -> String {
+    let mut parsed = match Url::parse(url) {
+        Ok(parsed)
+            if !parsed.username().is_empty()
+                || parsed.password().is_some()
+                || parsed.query().is_some() =>
+        {
+            parsed
+        }
+        Ok(_) => return url.to_string(),
+        Err(_) => return redact_unparsed(url),
+    };
+
+    if !parsed.username().is_empty() {
+        let _ = parsed.set_username("REDACTED");
+    }
+    if parsed.password().is_some() {
+        let _ = parsed.set_password(Some("REDACTED"));
+    }
+    let keys: Vec<String> = parsed
+        .query_pairs()
+        .map(|(key, _)| key.into_owned())
+        .collect();
+    if !keys.is_empty() {
+        parsed
+            .query_pairs_mut()
+            .clear()
+            .extend_pairs(keys.iter().map(|key| (key, "REDACTED")));
+    }
+
+    parsed.to_string()
+}
+
+/// Redacts the user info and query parameter values of a URL that cannot be
+/// parsed, e.g. one with an unbracketed IPv6 address.
+fn redact_unparsed(url: &str) -> String {
+    let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+    let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+    let (authority, rest) = rest.split_at(authority_end);
+    let (rest, fragment) = rest.split_at(rest.find('#').unwrap_or(rest.len()));
+
+    let mut redacted = String::new();
+    if !scheme.is_empty() {
+        redacted.push_str(scheme);
+        redacted.push_str("://");
+    }
+    match authority.rsplit_once('@') {
+        Some((_, host)) => {
+            redacted.push_str("REDACTED@");
+            redacted.push_str(host);
+        }
+        None => redacted.push_str(authority),
+    }
+    match rest.split_once('?') {
+        Some((path, query)) => {
+            redacted.push_str(path);
+            redacted.push('?');
+            let pairs: Vec<String> = query
+                .split('&')
+                .map(|pair| match pair.split_once('=') {
+                    Some((key, _)) => format!("{key}=REDACTED"),
+                    None => pair.to_owned(),
+                })
+                .collect();
+            redacted.push_str(&pairs.join("&"));
+        }
+        None => redacted.push_str(rest),
+    }
+    redacted.push_str(fragment);
+
+    redacted
+}
+
+/// Returns the HTTP URL of the same endpoint as a WebSocket URL, for JSON-RPC
+/// over HTTP: `ws://` becomes `http://` and `wss://` becomes `https://`.
+pub fn http_url(url: &str) -> Result<String> {
+    let mut parsed = Url::parse(url)?;
+    let scheme = match parsed.scheme() {
+        "ws" => "http",
+        "wss" => "https",
+        scheme => bail!("Unsupported URL scheme '{scheme}'"),
+    };
+    // Switching between special schemes always succeeds
+    let _ = parsed.set_scheme(scheme);
+
+    Ok(parsed.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_node_url, http_url, redact_url};
+
+    #[test]
+    fn accepts_bracketed_ipv6_literals() {
+        assert!(check_node_url("ws://[2001:db8::1]:9944").is_ok());
+        assert!(check_node_url("wss://[::1]/rpc").is_ok());
+        assert_eq!(
+            redact_url("ws://user:secret@[2001:db8::1]:9944"),
+            "ws://REDACTED:REDACTED@[2001:db8::1]:9944/"
+        );
+    }
+
+    #[test]
+    fn redacts_credentials() {
+        assert_eq!(
+            redact_url("wss://apikey@rpc.example.com/ws?token=abc&region=eu"),
+            "wss://REDACTED@rpc.example.com/ws?token=REDACTED&region=REDACTED"
+        );
+        // Path segments cannot be told apart from keys, so they are kept
+        assert_eq!(
+            redact_url("wss://mainnet.example.io/v3/0123abcd"),
+            "wss://mainnet.example.io/v3/0123abcd"
+        );
+    }
+
+    #[test]
+    fn derives_http_urls() {
+        assert_eq!(
+            http_url("wss://user:pw@rpc.example.com:8443/ws?key=1").unwrap(),
+            "https://user:pw@rpc.example.com:8443/ws?key=1"
+        );
+        assert_eq!(
+            http_url("ws://127.0.0.1:9944").unwrap(),
+            "http://127.0.0.1:9944/"
+        );
+    }
+
+    #[test]
+    fn explains_unbracketed_ipv6_literals() {
+        for url in ["ws://2001:db8::1:9944", "ws://::1:9944/rpc"] {
+            let error = check_node_url(url).unwrap_err().to_string();
+            assert!(error.contains("enclosed in brackets"), "{error}");
+        }
+        let error = check_node_url("ws://user:secret@2001:db8::1:9944/?key=abc")
+            .unwrap_err()
+            .to_string();
+        assert!(
+            error.contains("'ws://REDACTED@2001:db8::1:9944/?key=REDACTED'"),
+            "{error}"
+        );
+        assert!(
+            !check_node_url("ws://node:port")
+                .unwrap_err()
+                .to_string()
+                .contains("brackets")
+        );
+    }
+}
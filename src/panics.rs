@@ -0,0 +1,36 @@
+//! Logging and counting of panics, e.g. in HTTP handlers.
+//!
+//! actix-web silently replaces a worker whose handler panicked, so without a hook
+//! such panics only show up as invisible worker churn.
+
+use std::backtrace::Backtrace;
+use std::panic;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use tracing::{Level, event};
+
+/// Number of panics since startup.
+static PANICS: AtomicUsize = AtomicUsize::new(0);
+
+/// Replaces the default panic hook with one logging the panic and its backtrace
+/// as an ERROR event and counting it.
+///
+/// Must be called once the tracing subscriber is installed, as the default hook's
+/// output to stderr is replaced.
+pub fn install_hook() {
+    panic::set_hook(Box::new(|info| {
+        PANICS.fetch_add(1, Ordering::Relaxed);
+        let thread = thread::current();
+        let backtrace = Backtrace::force_capture();
+        event!(
+            Level::ERROR,
+            "Thread '{}' {info}\n{backtrace}",
+            thread.name().unwrap_or("<unnamed>")
+        );
+    }));
+}
+
+/// Returns the number of panics since startup.
+pub fn count() -> usize {
+    PANICS.load(Ordering::Relaxed)
+}
@@ -0,0 +1,84 @@
+//! Bounded history of the most recent check failures, for quick debugging.
+
+use crate::error_reason::ErrorReason;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A single recorded check failure.
+#[derive(Clone, Debug, Serialize)]
+pub struct ErrorRecord {
+    /// When the failure was recorded, as a Unix timestamp.
+    pub timestamp: u64,
+    /// Canonical failure reason, as used in metric labels.
+    pub reason: &'static str,
+    /// Full error message.
+    pub message: String,
+}
+
+/// Ring buffer of the last failures, oldest first.
+pub struct RecentErrors {
+    /// Maximum number of retained failures.
+    capacity: usize,
+    /// Retained failures, oldest first.
+    records: Mutex<VecDeque<ErrorRecord>>,
+}
+
+impl RecentErrors {
+    /// Creates an empty buffer retaining at most `capacity` failures.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            records: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Records a failure, dropping the oldest one if the buffer is full.
+    pub fn record(&self, reason: ErrorReason, message: &str) {
+        if self.capacity == 0 {
+            return;
+        }
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+
+        let mut records = self.records.lock().unwrap();
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(ErrorRecord {
+            timestamp,
+            reason: reason.as_str(),
+            message: message.to_owned(),
+        });
+    }
+
+    /// Returns the retained failures, oldest first.
+    pub fn snapshot(&self) -> Vec<ErrorRecord> {
+        self.records.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RecentErrors;
+    use crate::error_reason::ErrorReason;
+
+    #[test]
+    fn keeps_only_the_latest_failures() {
+        let errors = RecentErrors::new(2);
+        errors.record(ErrorReason::Refused, "first");
+        errors.record(ErrorReason::Timeout, "second");
+        errors.record(ErrorReason::Reset, "third");
+
+        let snapshot = errors.snapshot();
+        let messages: Vec<_> = snapshot.iter().map(|r| r.message.as_str()).collect();
+        assert_eq!(messages, ["second", "third"]);
+        assert_eq!(snapshot[0].reason, "timeout");
+
+        let disabled = RecentErrors::new(0);
+        disabled.record(ErrorReason::Other, "ignored");
+        assert!(disabled.snapshot().is_empty());
+    }
+}
@@ -0,0 +1,94 @@
+//! Detection of unexpected changes in responses that should be stable across checks.
+//!
+//! Fields like the chain name or genesis hash never change for a given node, so a
+//! change usually means a load balancer silently switched to a different backend.
+
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// A monitored field whose value differs from the previous check.
+#[derive(Debug, PartialEq, Eq)]
+pub struct FieldChange {
+    /// Name of the field.
+    pub field: &'static str,
+    /// Value seen in the previous check.
+    pub before: String,
+    /// Value seen in the current check.
+    pub after: String,
+}
+
+/// Remembers the last value of every monitored field and counts changes.
+#[derive(Default)]
+pub struct ResponseTracker {
+    /// Last value and change count per field.
+    fields: Mutex<BTreeMap<&'static str, (String, u64)>>,
+}
+
+impl ResponseTracker {
+    /// Compares the fields with the previous check and records the new values.
+    ///
+    /// The first value seen for a field becomes its baseline and is not a change.
+    pub fn observe(&self, values: Vec<(&'static str, String)>) -> Vec<FieldChange> {
+        let mut fields = self.fields.lock().unwrap();
+        let mut changes = Vec::new();
+        for (field, value) in values {
+            match fields.get_mut(field) {
+                Some((last, count)) if *last != value => {
+                    *count += 1;
+                    changes.push(FieldChange {
+                        field,
+                        before: std::mem::replace(last, value.clone()),
+                        after: value,
+                    });
+                }
+                Some(_) => {}
+                None => {
+                    fields.insert(field, (value, 0));
+                }
+            }
+        }
+
+        changes
+    }
+
+    /// Returns the change count of every field seen so far.
+    pub fn change_counts(&self) -> Vec<(&'static str, u64)> {
+        let fields = self.fields.lock().unwrap();
+        fields
+            .iter()
+            .map(|(field, (_, count))| (*field, *count))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{FieldChange, ResponseTracker};
+
+    #[test]
+    fn reports_changes_against_previous_check() {
+        let tracker = ResponseTracker::default();
+        assert!(
+            tracker
+                .observe(vec![("chain", "Polkadot".into())])
+                .is_empty()
+        );
+        assert!(
+            tracker
+                .observe(vec![("chain", "Polkadot".into())])
+                .is_empty()
+        );
+
+        let changes = tracker.observe(vec![("chain", "Kusama".into())]);
+        assert_eq!(
+            changes,
+            vec![FieldChange {
+                field: "chain",
+                before: "Polkadot".into(),
+                after: "Kusama".into(),
+            }]
+        );
+        assert!(tracker.observe(vec![("chain", "Kusama".into())]).is_empty());
+        assert_eq!(tracker.change_counts(), vec![("chain", 1)]);
+    }
+}
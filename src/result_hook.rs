@@ -0,0 +1,155 @@
+//! External command run after each monitor check, for custom integrations.
+
+use crate::alert_template::{AlertTemplate, AlertValues};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::time;
+use tracing::{Level, event};
+
+/// Runs a shell command after each check, with the check result in its environment.
+///
+/// At most one command runs at a time: results of checks finishing while the
+/// previous command is still running are dropped, so a slow command cannot pile
+/// up processes or stall the monitor.
+pub struct ResultHook {
+    /// Command passed to `sh -c`.
+    command: String,
+    /// Time after which a running command is killed.
+    timeout: Duration,
+    /// Template of the message passed in `WSMON_MESSAGE`, if any.
+    template: Option<AlertTemplate>,
+    /// Whether a command is currently running.
+    running: Arc<AtomicBool>,
+}
+
+impl ResultHook {
+    /// Creates a hook running `command` with the given timeout, passing a message
+    /// rendered from `template` if given.
+    pub fn new(command: String, timeout: Duration, template: Option<AlertTemplate>) -> Self {
+        Self {
+            command,
+            timeout,
+            template,
+            running: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Starts the command in the background for the result of a check.
+    ///
+    /// # Arguments
+    ///
+    /// * `endpoint` - The WebSocket endpoint being monitored (redacted)
+    /// * `result` - Result of the check, with the error chain on failure
+    /// * `duration` - Total duration of the check
+    /// * `up` - Whether the endpoint is considered up after the check
+    /// * `chain` - Chain name or ID reported by the node, if known
+    pub fn run(
+        &self,
+        endpoint: &str,
+        result: &Result<(), String>,
+        duration: Duration,
+        up: bool,
+        chain: Option<&str>,
+    ) {
+        if self.running.swap(true, Ordering::AcqRel) {
+            event!(
+                Level::DEBUG,
+                "Previous --on-result-exec command still running, skipping"
+            );
+            return;
+        }
+
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(&self.command)
+            .envs(hook_env(endpoint, result, duration))
+            .envs(self.template.as_ref().map(|template| {
+                let values = AlertValues {
+                    endpoint,
+                    up,
+                    error: result.as_ref().err().map(String::as_str),
+                    latency: duration,
+                    chain,
+                };
+                ("WSMON_MESSAGE", template.render(&values))
+            }))
+            .stdin(Stdio::null())
+            .kill_on_drop(true);
+        let timeout = self.timeout;
+        let running = self.running.clone();
+
+        tokio::spawn(async move {
+            match command.spawn() {
+                Ok(mut child) => match time::timeout(timeout, child.wait()).await {
+                    Ok(Ok(status)) if status.success() => {}
+                    Ok(Ok(status)) => {
+                        event!(Level::WARN, "--on-result-exec command failed: {status}");
+                    }
+                    Ok(Err(e)) => {
+                        event!(Level::WARN, "--on-result-exec command failed: {e}");
+                    }
+                    Err(_) => {
+                        // Dropping the child kills it
+                        event!(
+                            Level::WARN,
+                            "--on-result-exec command timed out after {}s, killed",
+                            timeout.as_secs()
+                        );
+                    }
+                },
+                Err(e) => {
+                    event!(Level::WARN, "Failed to start --on-result-exec command: {e}");
+                }
+            }
+            running.store(false, Ordering::Release);
+        });
+    }
+}
+
+/// Returns the environment variables describing a check result.
+fn hook_env(
+    endpoint: &str,
+    result: &Result<(), String>,
+    duration: Duration,
+) -> [(&'static str, String); 4] {
+    let (outcome, error) = match result {
+        Ok(()) => ("success", String::new()),
+        Err(error) => ("failure", error.clone()),
+    };
+
+    [
+        ("WSMON_ENDPOINT", endpoint.to_owned()),
+        ("WSMON_RESULT", outcome.to_owned()),
+        (
+            "WSMON_DURATION_SECONDS",
+            format!("{:.3}", duration.as_secs_f64()),
+        ),
+        ("WSMON_ERROR", error),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::hook_env;
+    use std::time::Duration;
+
+    #[test]
+    fn describes_check_result() {
+        let env = hook_env(
+            "wss://node",
+            &Err("Connection refused".into()),
+            Duration::from_millis(1500),
+        );
+        assert_eq!(
+            env.map(|(_, value)| value),
+            ["wss://node", "failure", "1.500", "Connection refused"]
+        );
+
+        let env = hook_env("wss://node", &Ok(()), Duration::ZERO);
+        assert_eq!((env[1].1.as_str(), env[3].1.as_str()), ("success", ""));
+    }
+}
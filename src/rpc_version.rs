@@ -0,0 +1,124 @@
+//! Translation between JSON-RPC 2.0, as spoken by jsonrpsee, and JSON-RPC 1.0 for
+//! legacy gateways rejecting 2.0 requests.
+//!
+//! Messages are translated on the WebSocket transport, so jsonrpsee itself keeps
+//! building and parsing 2.0 messages.
+
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::{Map, Value, json};
+
+/// Error code of 1.0 errors that are not 2.0 error objects.
+const LEGACY_ERROR_CODE: i64 = -32000;
+
+/// JSON-RPC protocol version of the requests sent to the node.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, ValueEnum)]
+pub enum RpcVersion {
+    /// JSON-RPC 2.0.
+    #[default]
+    #[value(name = "2.0")]
+    #[serde(rename = "2.0")]
+    V2,
+    /// JSON-RPC 1.0: no `jsonrpc` member, always positional `params`.
+    #[value(name = "1.0")]
+    #[serde(rename = "1.0")]
+    V1,
+}
+
+impl RpcVersion {
+    /// Converts a request built by jsonrpsee into this version.
+    ///
+    /// Messages that are not JSON objects, such as batches, are sent unchanged.
+    pub fn encode_request(self, request: String) -> String {
+        if self == RpcVersion::V2 {
+            return request;
+        }
+        let Some(mut object) = parse_object(&request) else {
+            return request;
+        };
+
+        object.remove("jsonrpc");
+        object
+            .entry("params")
+            .or_insert_with(|| Value::Array(Vec::new()));
+        Value::Object(object).to_string()
+    }
+
+    /// Converts a response of this version into the 2.0 format jsonrpsee expects.
+    ///
+    /// 1.0 responses carry both `result` and `error`, with the unused one `null`,
+    /// and errors need not be objects.
+    pub fn decode_response(self, response: String) -> String {
+        if self == RpcVersion::V2 {
+            return response;
+        }
+        let Some(mut object) = parse_object(&response) else {
+            return response;
+        };
+
+        match object.remove("error") {
+            None | Some(Value::Null) => {}
+            Some(error) => {
+                let error = match error {
+                    Value::Object(_) => error,
+                    Value::String(message) => {
+                        json!({ "code": LEGACY_ERROR_CODE, "message": message })
+                    }
+                    other => json!({ "code": LEGACY_ERROR_CODE, "message": other.to_string() }),
+                };
+                object.remove("result");
+                object.insert("error".to_owned(), error);
+            }
+        }
+        object.insert("jsonrpc".to_owned(), Value::from("2.0"));
+        Value::Object(object).to_string()
+    }
+}
+
+/// Parses a message as a JSON object.
+fn parse_object(message: &str) -> Option<Map<String, Value>> {
+    match serde_json::from_str(message) {
+        Ok(Value::Object(object)) => Some(object),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RpcVersion;
+    use serde_json::{Value, json};
+
+    fn translate(message: Value, f: impl Fn(String) -> String) -> Value {
+        serde_json::from_str(&f(message.to_string())).unwrap()
+    }
+
+    #[test]
+    fn encodes_legacy_requests() {
+        let request = json!({"jsonrpc": "2.0", "id": 0, "method": "system_health"});
+        assert_eq!(
+            translate(request.clone(), |r| RpcVersion::V1.encode_request(r)),
+            json!({"id": 0, "method": "system_health", "params": []})
+        );
+        assert_eq!(
+            translate(request.clone(), |r| RpcVersion::V2.encode_request(r)),
+            request
+        );
+    }
+
+    #[test]
+    fn decodes_legacy_responses() {
+        let decode = |response| translate(response, |r| RpcVersion::V1.decode_response(r));
+        assert_eq!(
+            decode(json!({"id": 0, "result": 5, "error": null})),
+            json!({"jsonrpc": "2.0", "id": 0, "result": 5})
+        );
+        assert_eq!(
+            decode(json!({"id": 0, "result": null, "error": "Unknown method"})),
+            json!({
+                "jsonrpc": "2.0",
+                "id": 0,
+                "error": {"code": -32000, "message": "Unknown method"}
+            })
+        );
+    }
+}
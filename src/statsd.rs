@@ -0,0 +1,97 @@
+//! Push of check results to a StatsD/DogStatsD sink, for environments without a
+//! Prometheus scraper.
+
+use anyhow::{Context, Result};
+use std::time::Duration;
+use tokio::net::{self, UdpSocket};
+use tracing::{Level, event};
+
+/// Prefix of all metric names sent to the sink.
+const PREFIX: &str = "ws_endpoint_monitor";
+
+/// UDP client sending the result of every check to a StatsD sink.
+pub struct StatsdSink {
+    /// Socket connected to the sink.
+    socket: UdpSocket,
+    /// Value of the `endpoint` tag.
+    endpoint: String,
+}
+
+impl StatsdSink {
+    /// Creates a client sending to the sink at `addr` (`host:port`).
+    ///
+    /// # Arguments
+    ///
+    /// * `addr` - Address of the StatsD sink
+    /// * `endpoint` - The WebSocket endpoint being monitored (redacted), sent as tag
+    pub async fn connect(addr: &str, endpoint: &str) -> Result<Self> {
+        let sink = net::lookup_host(addr)
+            .await
+            .with_context(|| format!("Failed to resolve StatsD address '{addr}'"))?
+            .next()
+            .with_context(|| format!("StatsD address '{addr}' resolved to no addresses"))?;
+        let socket = UdpSocket::bind(if sink.is_ipv4() {
+            "0.0.0.0:0"
+        } else {
+            "[::]:0"
+        })
+        .await?;
+        socket.connect(sink).await?;
+
+        Ok(Self {
+            socket,
+            endpoint: endpoint.to_owned(),
+        })
+    }
+
+    /// Sends the result of a check. Failures are only logged, as UDP delivery is
+    /// best-effort anyway.
+    ///
+    /// # Arguments
+    ///
+    /// * `success` - Whether the check succeeded
+    /// * `duration` - Total duration of the check
+    /// * `up` - Whether the endpoint is considered up after the check
+    pub async fn send_check(&self, success: bool, duration: Duration, up: bool) {
+        let packet = check_packet(&self.endpoint, success, duration, up);
+        if let Err(e) = self.socket.send(packet.as_bytes()).await {
+            event!(Level::DEBUG, "Failed to send metrics to StatsD: {e}");
+        }
+    }
+}
+
+/// Builds the packet describing a check, one metric per line with DogStatsD tags.
+fn check_packet(endpoint: &str, success: bool, duration: Duration, up: bool) -> String {
+    // Commas and pipes would end the tag list
+    let endpoint = endpoint.replace([',', '|'], "_");
+    let result = if success { "success" } else { "failure" };
+
+    [
+        format!("{PREFIX}.check_count:1|c|#endpoint:{endpoint},result:{result}"),
+        format!(
+            "{PREFIX}.check_duration:{:.3}|ms|#endpoint:{endpoint}",
+            duration.as_secs_f64() * 1000.0
+        ),
+        format!(
+            "{PREFIX}.endpoint_up:{}|g|#endpoint:{endpoint}",
+            u8::from(up)
+        ),
+    ]
+    .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_packet;
+    use std::time::Duration;
+
+    #[test]
+    fn formats_check_as_dogstatsd() {
+        assert_eq!(
+            check_packet("wss://node", false, Duration::from_millis(1250), true),
+            "ws_endpoint_monitor.check_count:1|c|#endpoint:wss://node,result:failure\n\
+             ws_endpoint_monitor.check_duration:1250.000|ms|#endpoint:wss://node\n\
+             ws_endpoint_monitor.endpoint_up:1|g|#endpoint:wss://node"
+        );
+    }
+}
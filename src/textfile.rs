@@ -0,0 +1,48 @@
+//! Periodic export of metrics to a file for node_exporter's textfile collector.
+
+use crate::{AppState, encode_metrics};
+use anyhow::Result;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::{fs, time};
+use tracing::{Level, event};
+
+/// Periodically writes the full metrics exposition to `path`.
+///
+/// This function runs indefinitely. Write failures are logged and retried on the
+/// next interval.
+///
+/// # Arguments
+///
+/// * `path` - Destination file of the exposition
+/// * `interval` - Time between writes
+/// * `state` - Shared application state the metrics are read from
+pub async fn textfile_writer(path: PathBuf, interval: Duration, state: AppState) {
+    let mut interval = time::interval(interval);
+
+    loop {
+        interval.tick().await;
+
+        let buffer = encode_metrics(&state.ws_endpoint, &state.snapshot());
+        if let Err(e) = write_atomically(&path, &buffer).await {
+            event!(
+                Level::WARN,
+                "Failed to write metrics to {}: {e:#}",
+                path.display()
+            );
+        }
+    }
+}
+
+/// Writes `contents` to a temporary file next to `path` and renames it into place,
+/// so readers never observe a partially written file.
+pub async fn write_atomically(path: &Path, contents: &[u8]) -> Result<()> {
+    let mut tmp_path = OsString::from(path);
+    tmp_path.push(".tmp");
+
+    fs::write(&tmp_path, contents).await?;
+    fs::rename(&tmp_path, path).await?;
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+//! Grouping of related metric updates, so scrapes never observe them half applied.
+//!
+//! The check counters, histograms and chain status are separate atomics and locks.
+//! Without grouping, a scrape running while a check is recorded could e.g. see its
+//! success counted but not yet its latency.
+//!
+//! Grouped are the values recorded once a check is done: the check and error
+//! counters, per-URL results, the connection phase, latency and duration
+//! histograms, the certificate expiry and the chain status. Values updated by the
+//! individual calls of a check as they happen, i.e. deserialization errors,
+//! custom gauges, node attributes and response changes, are not grouped.
+
+use std::sync::RwLock;
+
+/// Lock making groups of updates atomic with respect to readers.
+///
+/// Updates are brief and never held across an `await`, so a blocking lock is fine.
+#[derive(Default)]
+pub struct UpdateLock(RwLock<()>);
+
+impl UpdateLock {
+    /// Applies `update` as a unit: no [`UpdateLock::read`] runs concurrently.
+    pub fn update<R>(&self, update: impl FnOnce() -> R) -> R {
+        let _guard = self.0.write().unwrap();
+        update()
+    }
+
+    /// Runs `read` while no update is being applied.
+    pub fn read<R>(&self, read: impl FnOnce() -> R) -> R {
+        let _guard = self.0.read().unwrap();
+        read()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UpdateLock;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    #[test]
+    fn readers_never_see_partial_updates() {
+        let lock = Arc::new(UpdateLock::default());
+        let checks = Arc::new(AtomicUsize::new(0));
+        let latencies = Arc::new(AtomicUsize::new(0));
+
+        let writer = {
+            let (lock, checks, latencies) = (lock.clone(), checks.clone(), latencies.clone());
+            thread::spawn(move || {
+                for _ in 0..20_000 {
+                    lock.update(|| {
+                        checks.fetch_add(1, Ordering::Relaxed);
+                        thread::yield_now();
+                        latencies.fetch_add(1, Ordering::Relaxed);
+                    });
+                }
+            })
+        };
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let (lock, checks, latencies) = (lock.clone(), checks.clone(), latencies.clone());
+                thread::spawn(move || {
+                    for _ in 0..20_000 {
+                        let (checks, latencies) = lock.read(|| {
+                            (
+                                checks.load(Ordering::Relaxed),
+                                latencies.load(Ordering::Relaxed),
+                            )
+                        });
+                        assert_eq!(checks, latencies);
+                    }
+                })
+            })
+            .collect();
+
+        writer.join().unwrap();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        assert_eq!(checks.load(Ordering::Relaxed), 20_000);
+    }
+}
@@ -0,0 +1,95 @@
+//! Per-URL results when a target has fallback URLs.
+
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Tracks check results of every URL of the target and which one served last.
+pub struct UrlStats {
+    /// URLs in the order they are tried, primary first.
+    urls: Vec<String>,
+    /// Successful attempts per URL.
+    success: Vec<AtomicUsize>,
+    /// Failed attempts per URL.
+    failure: Vec<AtomicUsize>,
+    /// Index of the URL that served the last successful check, if any.
+    used: Mutex<Option<usize>>,
+}
+
+/// Point-in-time results of a single URL.
+pub struct UrlSnapshot {
+    /// The URL.
+    pub url: String,
+    /// Successful attempt count.
+    pub success: usize,
+    /// Failed attempt count.
+    pub failure: usize,
+    /// Whether this URL served the last successful check.
+    pub used: bool,
+}
+
+impl UrlStats {
+    /// Creates empty statistics for the given URLs.
+    pub fn new(urls: Vec<String>) -> Self {
+        Self {
+            success: urls.iter().map(|_| AtomicUsize::new(0)).collect(),
+            failure: urls.iter().map(|_| AtomicUsize::new(0)).collect(),
+            urls,
+            used: Mutex::new(None),
+        }
+    }
+
+    /// Returns whether the target has fallback URLs besides the primary one.
+    pub fn has_fallbacks(&self) -> bool {
+        self.urls.len() > 1
+    }
+
+    /// Records the result of an attempt against the URL at `index`.
+    ///
+    /// A successful attempt also marks the URL as the one that served the check.
+    pub fn record(&self, index: usize, success: bool) {
+        if success {
+            self.success[index].fetch_add(1, Ordering::Relaxed);
+            *self.used.lock().unwrap() = Some(index);
+        } else {
+            self.failure[index].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the current results of every URL.
+    pub fn snapshot(&self) -> Vec<UrlSnapshot> {
+        let used = *self.used.lock().unwrap();
+        self.urls
+            .iter()
+            .enumerate()
+            .map(|(index, url)| UrlSnapshot {
+                url: url.clone(),
+                success: self.success[index].load(Ordering::Relaxed),
+                failure: self.failure[index].load(Ordering::Relaxed),
+                used: used == Some(index),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UrlStats;
+
+    #[test]
+    fn tracks_url_that_served_last_check() {
+        let stats = UrlStats::new(vec!["ws://a".into(), "ws://b".into()]);
+        stats.record(0, false);
+        stats.record(1, true);
+
+        let snapshot = stats.snapshot();
+        assert_eq!((snapshot[0].success, snapshot[0].failure), (0, 1));
+        assert_eq!((snapshot[1].success, snapshot[1].failure), (1, 0));
+        assert!(!snapshot[0].used);
+        assert!(snapshot[1].used);
+
+        stats.record(0, true);
+        let snapshot = stats.snapshot();
+        assert!(snapshot[0].used);
+        assert!(!snapshot[1].used);
+    }
+}